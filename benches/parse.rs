@@ -0,0 +1,74 @@
+//! Compares the allocations and time of `parse` and `parse_borrowed` on a
+//! description with several paragraphs, lists and a code block. Run with
+//! `cargo bench`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use prlist::text;
+
+const ITERATIONS: usize = 10_000;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn description() -> String {
+    let section = "This change adds the widgets that were asked for in the last sprint.\n\
+                   \n\
+                   Changes:\n\
+                   - Add the widget model\n\
+                   - Store widgets in the database\n\
+                   - Show widgets on the front page\n\
+                   \n\
+                   1. Run the migrations\n\
+                   2. Restart the service\n\
+                   \n\
+                   ```\n\
+                   cargo run --release\n\
+                   ```\n\
+                   \n";
+    section.repeat(10)
+}
+
+/// Runs `parse`, which returns the number of elements, on `description`.
+fn measure(name: &str, parse: impl Fn(&str) -> usize, description: &str) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(parse(black_box(description)));
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    println!(
+        "{name:>14}: {:>6} allocations, {:>8.2?} per parse",
+        allocations / ITERATIONS,
+        elapsed / ITERATIONS as u32
+    );
+}
+
+fn main() {
+    let description = description();
+    measure("parse", |d| black_box(text::parse(d)).len(), &description);
+    measure(
+        "parse_borrowed",
+        |d| black_box(text::parse_borrowed(d)).len(),
+        &description,
+    );
+}
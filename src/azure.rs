@@ -0,0 +1,1100 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use early::Early;
+use futures::{stream, StreamExt, TryStreamExt};
+use log::{debug, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::cache::RepositoryCache;
+use crate::error::{ApiError, PrListError, Result};
+
+const PAGE_SIZE: usize = 100;
+const CONCURRENT_REQUESTS: usize = 8;
+const TRUNCATED_DESCRIPTION_LENGTH: usize = 400;
+const MAX_RATE_LIMITED_ATTEMPTS: usize = 5;
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(10);
+pub const DEFAULT_API_PATH: &str = "_apis";
+/// The id of Azure DevOps as a resource in Microsoft Entra ID, to get access
+/// tokens for.
+pub const AZURE_DEVOPS_RESOURCE: &str = "499b84ac-1321-427f-aa17-267ca6975798";
+
+#[derive(Debug, Deserialize)]
+struct Reply<T> {
+    /// Missing or null when there is nothing to list.
+    value: Option<Vec<T>>,
+}
+
+/// What a file given to [read_pull_requests] may contain: either a reply as
+/// returned by Azure DevOps or a plain array as printed by `--format json`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedPullRequests {
+    Reply(Reply<PullRequest>),
+    List(Vec<PullRequest>),
+}
+
+pub fn read_pull_requests(path: &Path) -> Result<Vec<PullRequest>> {
+    let content = std::fs::read_to_string(path).map_err(|source| PrListError::Io {
+        action: "read pull requests from",
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_saved_pull_requests(&content).map_err(|source| PrListError::Decode {
+        what: format!("pull requests in {}", path.display()),
+        source,
+    })
+}
+
+fn parse_saved_pull_requests(json: &str) -> serde_json::Result<Vec<PullRequest>> {
+    Ok(match serde_json::from_str(json)? {
+        SavedPullRequests::Reply(reply) => reply.value.unwrap_or_default(),
+        SavedPullRequests::List(list) => list,
+    })
+}
+
+/// A comparison of two commits, of which only the number of changed files is
+/// used.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitDiffs {
+    #[serde(default)]
+    change_counts: BTreeMap<String, usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Repository {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequest {
+    pub title: String,
+    pub description: Option<String>,
+    pub is_draft: bool,
+    pub pull_request_id: u32,
+    pub created_by: Person,
+    pub source_ref_name: String,
+    pub target_ref_name: String,
+    pub creation_date: DateTime<Utc>,
+    /// When the pull request was completed or abandoned.
+    pub closed_date: Option<DateTime<Utc>>,
+    pub status: String,
+    #[serde(default)]
+    pub reviewers: Vec<Reviewer>,
+    pub repository: Repository,
+    #[serde(default)]
+    pub work_item_refs: Vec<ResourceRef>,
+    pub merge_status: Option<String>,
+    /// The number of changed files by kind of change, like `Edit`, if
+    /// fetched with [AzureClient::fetch_change_counts].
+    #[serde(skip)]
+    pub change_counts: Option<BTreeMap<String, usize>>,
+    pub last_merge_source_commit: Option<CommitRef>,
+    pub last_merge_target_commit: Option<CommitRef>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+    #[serde(default)]
+    pub threads: Vec<Thread>,
+}
+
+impl PullRequest {
+    pub fn source_branch(&self) -> &str {
+        branch_name(&self.source_ref_name)
+    }
+
+    pub fn target_branch(&self) -> &str {
+        branch_name(&self.target_ref_name)
+    }
+
+    /// True if the pull request merges into `branch`, given with or without
+    /// `refs/heads/`.
+    pub fn merges_into(&self, branch: &str) -> bool {
+        self.target_ref_name == ref_name(branch)
+    }
+
+    /// True if `username` is a reviewer who hasn't voted yet.
+    pub fn is_awaiting_review_by(&self, username: &str) -> bool {
+        self.reviewers
+            .iter()
+            .any(|reviewer| reviewer.person.is(username) && reviewer.vote == 0)
+    }
+
+    /// How many reviewers approved, are waiting for the author and rejected.
+    pub fn approvals(&self) -> Approvals {
+        let mut approvals = Approvals::default();
+        for reviewer in &self.reviewers {
+            match reviewer.vote {
+                10 | 5 => approvals.approved += 1,
+                -5 => approvals.waiting += 1,
+                -10 => approvals.rejected += 1,
+                _ => {}
+            }
+        }
+        approvals
+    }
+
+    /// The abbreviated id of the commit at the tip of the source branch.
+    pub fn short_source_commit(&self) -> Option<&str> {
+        let commit_id = &self.last_merge_source_commit.as_ref()?.commit_id;
+        Some(commit_id.get(..7).unwrap_or(commit_id))
+    }
+
+    /// True if the pull request has all of `names` as labels, ignoring case.
+    pub fn has_labels(&self, names: &[String]) -> bool {
+        names.iter().all(|name| {
+            self.labels
+                .iter()
+                .any(|label| label.name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// The number of comments written by people, not by Azure DevOps.
+    pub fn comment_count(&self) -> usize {
+        self.discussion().map(|thread| thread.comments.len()).sum()
+    }
+
+    pub fn unresolved_thread_count(&self) -> usize {
+        self.discussion()
+            .filter(|thread| matches!(thread.status.as_deref(), Some("active" | "pending")))
+            .count()
+    }
+
+    fn discussion(&self) -> impl Iterator<Item = &Thread> {
+        self.threads.iter().filter(|thread| {
+            !thread.is_deleted
+                && thread
+                    .comments
+                    .iter()
+                    .any(|comment| comment.comment_type != "system")
+        })
+    }
+
+    pub fn has_conflicts(&self) -> bool {
+        self.merge_status.as_deref() == Some("conflicts")
+    }
+
+    fn has_truncated_description(&self) -> bool {
+        self.description
+            .as_ref()
+            .is_some_and(|d| d.chars().count() >= TRUNCATED_DESCRIPTION_LENGTH)
+    }
+}
+
+/// The full name of `branch`, like `refs/heads/main` for `main`.
+fn ref_name(branch: &str) -> String {
+    if branch.starts_with("refs/") {
+        branch.to_string()
+    } else {
+        format!("refs/heads/{branch}")
+    }
+}
+
+fn branch_name(ref_name: &str) -> &str {
+    ref_name.strip_prefix("refs/heads/").unwrap_or(ref_name)
+}
+
+/// Someone creating or reviewing pull requests.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Person {
+    pub display_name: String,
+    /// The email address or UPN, which unlike the display name is unique.
+    #[serde(default)]
+    pub unique_name: String,
+}
+
+impl Person {
+    pub fn is(&self, username: &str) -> bool {
+        self.unique_name.eq_ignore_ascii_case(username)
+            || self.display_name.eq_ignore_ascii_case(username)
+    }
+
+    /// True if any of `names` is part of the display name or unique name,
+    /// ignoring case.
+    pub fn matches_any(&self, names: &[String]) -> bool {
+        let display_name = self.display_name.to_lowercase();
+        let unique_name = self.unique_name.to_lowercase();
+        names.iter().any(|name| {
+            let name = name.to_lowercase();
+            display_name.contains(&name) || unique_name.contains(&name)
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reviewer {
+    #[serde(flatten)]
+    pub person: Person,
+    pub vote: i32,
+}
+
+impl Reviewer {
+    pub fn vote_description(&self) -> &'static str {
+        match self.vote {
+            10 => "approved",
+            5 => "approved with suggestions",
+            0 => "no vote",
+            -5 => "waiting for author",
+            -10 => "rejected",
+            _ => "unknown vote",
+        }
+    }
+}
+
+/// The votes of the reviewers of a pull request, where approving with
+/// suggestions counts as approving.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Approvals {
+    pub approved: usize,
+    pub waiting: usize,
+    pub rejected: usize,
+}
+
+impl Approvals {
+    /// True if someone approved and no one rejected.
+    pub fn is_ready(&self) -> bool {
+        self.approved > 0 && self.rejected == 0
+    }
+}
+
+impl std::fmt::Display for Approvals {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} approved, {} waiting, {} rejected",
+            self.approved, self.waiting, self.rejected
+        )
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitRef {
+    pub commit_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Label {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Thread {
+    pub status: Option<String>,
+    #[serde(default)]
+    pub is_deleted: bool,
+    #[serde(default)]
+    pub comments: Vec<Comment>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comment {
+    #[serde(default)]
+    pub comment_type: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourceRef {
+    pub id: String,
+}
+
+pub struct PullRequestQuery<'a> {
+    pub repository: Option<&'a str>,
+    pub status: &'a str,
+    /// The branch the pull requests merge into, with or without `refs/heads/`.
+    pub target: Option<&'a str>,
+    pub max: Option<usize>,
+}
+
+/// How [AzureClient] authenticates with Azure DevOps.
+#[derive(Clone)]
+pub enum Credentials {
+    /// A personal access token, sent with basic authentication.
+    Pat { username: String, pat: String },
+    /// An access token, e.g. from [az_cli_token], sent as a bearer token.
+    Bearer(String),
+}
+
+impl Credentials {
+    /// What the credentials are, for telling the user what to check.
+    fn description(&self) -> &'static str {
+        match self {
+            Credentials::Pat { .. } => "the username and PAT",
+            Credentials::Bearer(_) => "the access token",
+        }
+    }
+}
+
+pub struct AzureClient {
+    client: reqwest::Client,
+    credentials: Credentials,
+    base: Early,
+    organization: String,
+    project: String,
+    api_path: Vec<String>,
+    api_version: String,
+    repository_cache: RepositoryCache,
+}
+
+/// Builds the HTTP client to give [AzureClient::new]. Without an explicit
+/// `proxy`, reqwest picks one up from `HTTPS_PROXY`/`HTTP_PROXY`.
+pub fn http_client(
+    proxy: Option<&str>,
+    insecure: bool,
+    timeout: Duration,
+) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder().timeout(timeout);
+    if let Some(proxy) = proxy {
+        let proxy = reqwest::Proxy::all(proxy).map_err(|source| PrListError::Config {
+            message: format!("Invalid proxy URL {proxy}"),
+            source: Some(source),
+        })?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .danger_accept_invalid_certs(insecure)
+        .build()
+        .map_err(|source| PrListError::Config {
+            message: "Failed to create HTTP client".into(),
+            source: Some(source),
+        })
+}
+
+/// Gets an access token for Azure DevOps from the Azure CLI, which has to be
+/// logged in with `az login`.
+pub fn az_cli_token() -> Result<String> {
+    let program = if cfg!(windows) { "az.cmd" } else { "az" };
+    let output = std::process::Command::new(program)
+        .args(["account", "get-access-token", "--resource"])
+        .arg(AZURE_DEVOPS_RESOURCE)
+        .args(["--query", "accessToken", "--output", "tsv"])
+        .output()
+        .map_err(|source| PrListError::AzureCli {
+            message: "Failed to run az, is the Azure CLI installed?".into(),
+            source: Some(source),
+        })?;
+    if !output.status.success() {
+        return Err(PrListError::AzureCli {
+            message: format!(
+                "The Azure CLI couldn't get an access token, are you logged in with az login?\n{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+            source: None,
+        });
+    }
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if token.is_empty() {
+        return Err(PrListError::AzureCli {
+            message: "The Azure CLI returned an empty access token".into(),
+            source: None,
+        });
+    }
+    Ok(token)
+}
+
+impl AzureClient {
+    pub fn new(
+        client: reqwest::Client,
+        organization: &str,
+        project: &str,
+        credentials: Credentials,
+        api_version: &str,
+    ) -> Self {
+        AzureClient {
+            client,
+            credentials,
+            base: Early::new("https", "dev.azure.com"),
+            organization: organization.into(),
+            project: project.into(),
+            api_path: vec![DEFAULT_API_PATH.into()],
+            api_version: api_version.into(),
+            repository_cache: RepositoryCache::new(),
+        }
+    }
+
+    /// Uses the REST API under `api_path` in the project instead of under
+    /// `_apis`, e.g. for previews of new endpoints.
+    pub fn with_api_path(mut self, api_path: &str) -> Self {
+        self.api_path = api_path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect();
+        self
+    }
+
+    /// Talks to the server at `base_url`, like an Azure DevOps Server, instead
+    /// of `https://dev.azure.com`.
+    pub fn with_base_url(mut self, base_url: &str) -> Result<Self> {
+        let invalid = |reason: &str| PrListError::Config {
+            message: format!("Invalid base URL {base_url}: {reason}"),
+            source: None,
+        };
+        let url = reqwest::Url::parse(base_url).map_err(|error| invalid(&error.to_string()))?;
+        let host = url.host_str().ok_or_else(|| invalid("there is no host"))?;
+        let mut base = Early::new(url.scheme(), host);
+        if let Some(port) = url.port() {
+            base = base.port(port);
+        }
+        for segment in url.path_segments().into_iter().flatten() {
+            if !segment.is_empty() {
+                base = base.path(segment);
+            }
+        }
+        self.base = base;
+        Ok(self)
+    }
+
+    pub fn web_url(&self, pr: &PullRequest) -> String {
+        self.project_web()
+            .path("_git")
+            .path(&pr.repository.name)
+            .path("pullrequest")
+            .path(pr.pull_request_id.to_string())
+            .build()
+    }
+
+    /// The page where the user can create personal access tokens.
+    pub fn tokens_web_url(&self) -> String {
+        self.organization_web()
+            .path("_usersSettings")
+            .path("tokens")
+            .build()
+    }
+
+    /// The page listing all the pull requests in the project.
+    pub fn pull_requests_web_url(&self) -> String {
+        self.project_web().path("_pulls").build()
+    }
+
+    pub async fn repositories(&self) -> Result<Vec<Repository>> {
+        let url = self.git_url(&["repositories"], &[]);
+        let reply: Reply<Repository> = self.get_json(&url, "repositories").await?;
+        Ok(reply.value.unwrap_or_default())
+    }
+
+    pub async fn repository(&self, name: &str) -> Result<Repository> {
+        let url = self.git_url(&["repositories", name], &[]);
+        self.get_json(&url, "repository").await
+    }
+
+    pub async fn list_pull_requests(
+        &self,
+        query: &PullRequestQuery<'_>,
+    ) -> Result<Vec<PullRequest>> {
+        let Some(name) = query.repository else {
+            return self.list_pull_requests_at(&["pullrequests"], query).await;
+        };
+        let cache_key = format!("{}/{name}", self.project_web().build());
+        if let Some(id) = self.repository_cache.get(&cache_key) {
+            let segments = ["repositories", &id, "pullrequests"];
+            match self.list_pull_requests_at(&segments, query).await {
+                Ok(pull_requests) => return Ok(pull_requests),
+                Err(error) => {
+                    debug!("Looking up {name} again after using its cached id failed: {error:#}");
+                    self.repository_cache.remove(&cache_key);
+                }
+            }
+        }
+        let repository = self.repository(name).await?;
+        self.repository_cache.insert(&cache_key, &repository.id);
+        let segments = ["repositories", &repository.id, "pullrequests"];
+        self.list_pull_requests_at(&segments, query).await
+    }
+
+    async fn list_pull_requests_at(
+        &self,
+        segments: &[&str],
+        query: &PullRequestQuery<'_>,
+    ) -> Result<Vec<PullRequest>> {
+        let target = query.target.map(ref_name);
+        let mut pull_requests: Vec<PullRequest> = vec![];
+        loop {
+            let top = match query.max {
+                Some(max) => PAGE_SIZE.min(max.saturating_sub(pull_requests.len())),
+                None => PAGE_SIZE,
+            };
+            if top == 0 {
+                break;
+            }
+            let page_size = top.to_string();
+            let skip = pull_requests.len().to_string();
+            let mut parameters = vec![("searchCriteria.status", query.status)];
+            if let Some(target) = &target {
+                parameters.push(("searchCriteria.targetRefName", target));
+            }
+            parameters.extend([("$top", page_size.as_str()), ("$skip", skip.as_str())]);
+            let url = self.git_url(segments, &parameters);
+            let page: Reply<PullRequest> = self.get_json(&url, "PR list").await?;
+            let page = page.value.unwrap_or_default();
+            let page_len = page.len();
+            pull_requests.extend(page);
+            if let Some(max) = query.max {
+                pull_requests.truncate(max);
+            }
+            if page_len < top {
+                break;
+            }
+        }
+        Ok(pull_requests)
+    }
+
+    pub async fn pull_request(&self, id: u32) -> Result<PullRequest> {
+        let url = self.git_url(&["pullrequests", &id.to_string()], &[]);
+        self.get_json(&url, "PR details").await
+    }
+
+    /// Replaces the pull requests whose description was truncated by the
+    /// list endpoint with the full pull request, keeping their order.
+    pub async fn complete_descriptions(&self, pull_requests: &mut [PullRequest]) -> Result<()> {
+        let truncated: Vec<(usize, u32)> = pull_requests
+            .iter()
+            .enumerate()
+            .filter(|(_, pr)| pr.has_truncated_description())
+            .map(|(index, pr)| (index, pr.pull_request_id))
+            .collect();
+        let details: Vec<(usize, PullRequest)> = stream::iter(truncated)
+            .map(|(index, id)| async move {
+                Ok::<_, PrListError>((index, self.pull_request(id).await?))
+            })
+            .buffer_unordered(CONCURRENT_REQUESTS)
+            .try_collect()
+            .await?;
+        for (index, pr) in details {
+            pull_requests[index] = pr;
+        }
+        Ok(())
+    }
+
+    /// Fills in the work items linked to each of the pull requests.
+    pub async fn fetch_work_item_refs(&self, pull_requests: &mut [PullRequest]) -> Result<()> {
+        let work_item_refs = fetch_each(pull_requests, |pr| self.work_item_refs(pr)).await?;
+        for (pr, work_item_refs) in pull_requests.iter_mut().zip(work_item_refs) {
+            pr.work_item_refs = work_item_refs;
+        }
+        Ok(())
+    }
+
+    /// Fills in the comment threads of each of the pull requests.
+    pub async fn fetch_threads(&self, pull_requests: &mut [PullRequest]) -> Result<()> {
+        let threads = fetch_each(pull_requests, |pr| self.threads(pr)).await?;
+        for (pr, threads) in pull_requests.iter_mut().zip(threads) {
+            pr.threads = threads;
+        }
+        Ok(())
+    }
+
+    /// Fills in how many files each of the pull requests changes. Pull
+    /// requests whose changes can't be fetched are left without them.
+    pub async fn fetch_change_counts(&self, pull_requests: &mut [PullRequest]) -> Result<()> {
+        let change_counts = fetch_each(pull_requests, |pr| async move {
+            match self.change_counts(pr).await {
+                Ok(change_counts) => Ok(change_counts),
+                Err(error) => {
+                    warn!(
+                        "Leaving out the changes of pull request {}: {error}",
+                        pr.pull_request_id
+                    );
+                    Ok(None)
+                }
+            }
+        })
+        .await?;
+        for (pr, change_counts) in pull_requests.iter_mut().zip(change_counts) {
+            pr.change_counts = change_counts;
+        }
+        Ok(())
+    }
+
+    /// The changes between the source commit of the last merge and where it
+    /// forked from the target commit, which is what the pull request merges.
+    /// The commits are used rather than the branches, as the source branch
+    /// may be deleted or, for forks, in another repository, and the target
+    /// branch already contains the commits of a completed pull request.
+    async fn change_counts(&self, pr: &PullRequest) -> Result<Option<BTreeMap<String, usize>>> {
+        let (Some(source), Some(target)) =
+            (&pr.last_merge_source_commit, &pr.last_merge_target_commit)
+        else {
+            return Ok(None);
+        };
+        let url = self.git_url(
+            &["repositories", &pr.repository.id, "diffs", "commits"],
+            &[
+                ("baseVersion", &target.commit_id),
+                ("baseVersionType", "commit"),
+                ("targetVersion", &source.commit_id),
+                ("targetVersionType", "commit"),
+                ("diffCommonCommit", "true"),
+                ("$top", "1"),
+            ],
+        );
+        let diffs: CommitDiffs = self.get_json(&url, "changes").await?;
+        Ok(Some(diffs.change_counts))
+    }
+
+    async fn threads(&self, pr: &PullRequest) -> Result<Vec<Thread>> {
+        let url = self.pull_request_url(pr, "threads");
+        let reply: Reply<Thread> = self.get_json(&url, "comment threads").await?;
+        Ok(reply.value.unwrap_or_default())
+    }
+
+    async fn work_item_refs(&self, pr: &PullRequest) -> Result<Vec<ResourceRef>> {
+        let url = self.pull_request_url(pr, "workitems");
+        let reply: Reply<ResourceRef> = self.get_json(&url, "linked work items").await?;
+        Ok(reply.value.unwrap_or_default())
+    }
+
+    fn organization_web(&self) -> Early {
+        self.base.clone().path(&self.organization)
+    }
+
+    fn project_web(&self) -> Early {
+        self.organization_web().path(&self.project)
+    }
+
+    /// The URL of `resource`, like `threads`, of a pull request.
+    fn pull_request_url(&self, pr: &PullRequest, resource: &str) -> String {
+        let id = pr.pull_request_id.to_string();
+        self.git_url(
+            &[
+                "repositories",
+                &pr.repository.id,
+                "pullRequests",
+                &id,
+                resource,
+            ],
+            &[],
+        )
+    }
+
+    fn git_url(&self, segments: &[&str], query: &[(&str, &str)]) -> String {
+        self.api_url(&[&["git"], segments].concat(), query)
+    }
+
+    /// The URL of the REST API resource at `segments` in the project, with
+    /// the `query` parameters after the API version.
+    fn api_url(&self, segments: &[&str], query: &[(&str, &str)]) -> String {
+        let mut url = self.project_web();
+        for segment in self
+            .api_path
+            .iter()
+            .map(String::as_str)
+            .chain(segments.iter().copied())
+        {
+            url = url.path(segment);
+        }
+        url = url.query("api-version", &self.api_version);
+        for (key, value) in query {
+            url = url.query(*key, *value);
+        }
+        url.build()
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str, what: &str) -> Result<T> {
+        let mut attempt = 1;
+        let response = loop {
+            debug!("GET {url}");
+            let start = Instant::now();
+            let request = match &self.credentials {
+                Credentials::Pat { username, pat } => {
+                    self.client.get(url).basic_auth(username, Some(pat))
+                }
+                Credentials::Bearer(token) => self.client.get(url).bearer_auth(token),
+            };
+            let response = match request.send().await {
+                Ok(response) => response,
+                Err(error) if error.is_timeout() => {
+                    return Err(PrListError::Timeout {
+                        what: what.into(),
+                        url: url.into(),
+                    })
+                }
+                Err(source) => {
+                    return Err(PrListError::Network {
+                        what: what.into(),
+                        url: url.into(),
+                        source,
+                    })
+                }
+            };
+            debug!(
+                "GET {url}: {} after {:?}",
+                response.status(),
+                start.elapsed()
+            );
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+                || attempt == MAX_RATE_LIMITED_ATTEMPTS
+            {
+                break response;
+            }
+            let delay = retry_after(response.headers());
+            warn!("Rate limited by Azure DevOps, retrying in {delay:?}");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|source| PrListError::Network {
+                what: what.into(),
+                url: url.into(),
+                source,
+            })?;
+        if is_expired_pat(status, &body) {
+            return Err(PrListError::ExpiredPat {
+                what: what.into(),
+                url: url.into(),
+                tokens_url: self.tokens_web_url(),
+            });
+        }
+        // Azure DevOps answers a PAT it doesn't know with a sign-in page
+        // marked as non-authoritative instead of a 401.
+        if status == reqwest::StatusCode::UNAUTHORIZED
+            || status == reqwest::StatusCode::FORBIDDEN
+            || status == reqwest::StatusCode::NON_AUTHORITATIVE_INFORMATION
+        {
+            return Err(PrListError::Auth {
+                what: what.into(),
+                url: url.into(),
+                status,
+                credentials: self.credentials.description(),
+            });
+        }
+        if !status.is_success() {
+            return Err(match serde_json::from_str::<ApiError>(&body) {
+                Ok(source) => PrListError::Api {
+                    what: what.into(),
+                    url: url.into(),
+                    status,
+                    source,
+                },
+                Err(_) => PrListError::Status {
+                    what: what.into(),
+                    url: url.into(),
+                    status,
+                    body: body.trim().into(),
+                },
+            });
+        }
+        parse_json(&body, what, url, status)
+    }
+}
+
+/// Whether Azure DevOps refused the request because the PAT has expired,
+/// which it says in the message of the error payload or page it replies with.
+fn is_expired_pat(status: reqwest::StatusCode, body: &str) -> bool {
+    if status != reqwest::StatusCode::UNAUTHORIZED
+        && status != reqwest::StatusCode::NON_AUTHORITATIVE_INFORMATION
+    {
+        return false;
+    }
+    let message = match serde_json::from_str::<ApiError>(body) {
+        Ok(error) => error.message,
+        Err(_) => body.to_string(),
+    };
+    let message = message.to_lowercase();
+    message.contains("expired") && (message.contains("token") || message.contains("pat"))
+}
+
+/// Parses a successful reply. An error payload in place of what was asked for
+/// is reported as an [ApiError], rather than as a mismatch with `T` or, for a
+/// [Reply], as an empty list.
+fn parse_json<T: DeserializeOwned>(
+    body: &str,
+    what: &str,
+    url: &str,
+    status: reqwest::StatusCode,
+) -> Result<T> {
+    if let Ok(source) = serde_json::from_str::<ApiError>(body) {
+        return Err(PrListError::Api {
+            what: what.into(),
+            url: url.into(),
+            status,
+            source,
+        });
+    }
+    serde_json::from_str(body).map_err(|source| PrListError::Decode {
+        what: what.into(),
+        source,
+    })
+}
+
+/// How long the server asked us to wait before the next request, given in
+/// seconds or as a date in the `Retry-After` header.
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Duration {
+    let Some(value) = headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return DEFAULT_RETRY_AFTER;
+    };
+    if let Ok(seconds) = value.trim().parse() {
+        return Duration::from_secs(seconds);
+    }
+    match DateTime::parse_from_rfc2822(value) {
+        Ok(date) => (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO),
+        Err(_) => DEFAULT_RETRY_AFTER,
+    }
+}
+
+/// Runs `fetch` for each of the pull requests with a bounded number of
+/// requests in flight and returns the results in the same order.
+async fn fetch_each<'a, T, F, Fut>(pull_requests: &'a [PullRequest], fetch: F) -> Result<Vec<T>>
+where
+    F: Fn(&'a PullRequest) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    stream::iter(pull_requests)
+        .map(fetch)
+        .buffered(CONCURRENT_REQUESTS)
+        .try_collect()
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::StatusCode;
+
+    fn client() -> AzureClient {
+        AzureClient::new(
+            reqwest::Client::new(),
+            "contoso",
+            "webshop",
+            Credentials::Pat {
+                username: "jane".into(),
+                pat: "secret".into(),
+            },
+            "7.1",
+        )
+    }
+
+    fn sample_pull_request() -> PullRequest {
+        serde_json::from_str(
+            r#"{
+                "title": "Add widgets",
+                "description": "Adds the widgets.",
+                "isDraft": false,
+                "pullRequestId": 42,
+                "createdBy": { "displayName": "Jane Doe", "uniqueName": "jane@contoso.com" },
+                "sourceRefName": "refs/heads/feature/widgets",
+                "targetRefName": "refs/heads/main",
+                "creationDate": "2022-11-20T10:15:00Z",
+                "status": "active",
+                "reviewers": [],
+                "repository": { "id": "1234", "name": "web shop" }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn saved_pull_requests_can_be_a_reply_or_a_list() {
+        let pr = serde_json::to_string(&sample_pull_request()).unwrap();
+        for json in [format!("{{\"value\": [{pr}]}}"), format!("[{pr}]")] {
+            let pull_requests = parse_saved_pull_requests(&json).unwrap();
+            assert_eq!(pull_requests.len(), 1);
+            assert_eq!(pull_requests[0].pull_request_id, 42);
+        }
+    }
+
+    #[test]
+    fn error_payloads_are_reported_as_api_errors() {
+        let body = r#"{
+            "$id": "1",
+            "innerException": null,
+            "message": "TF401019: The Git repository with name or identifier shop does not exist.",
+            "typeName": "Microsoft.TeamFoundation.Git.Server.GitRepositoryNotFoundException",
+            "typeKey": "GitRepositoryNotFoundException",
+            "errorCode": 0,
+            "eventId": 3000
+        }"#;
+        let url = "https://dev.azure.com/contoso/webshop/_apis/git/repositories";
+        let error =
+            parse_json::<Reply<Repository>>(body, "repositories", url, StatusCode::OK).unwrap_err();
+        let PrListError::Api { source, .. } = error else {
+            panic!("expected an API error, got {error:?}");
+        };
+        assert!(source.message.starts_with("TF401019"));
+        assert_eq!(
+            source.type_key.as_deref(),
+            Some("GitRepositoryNotFoundException")
+        );
+        for body in [r#"{"count": 0}"#, r#"{"count": 0, "value": null}"#] {
+            let reply: Reply<PullRequest> =
+                parse_json(body, "PR list", url, StatusCode::OK).unwrap();
+            assert_eq!(reply.value.unwrap_or_default().len(), 0);
+        }
+        assert!(parse_saved_pull_requests(r#"{"count": 0, "value": null}"#)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn expired_pats_are_recognized_by_status_and_message() {
+        let expired = r#"{
+            "$id": "1",
+            "message": "The Personal Access Token used has expired.",
+            "typeKey": "UnauthorizedRequestException"
+        }"#;
+        assert!(is_expired_pat(StatusCode::UNAUTHORIZED, expired));
+        assert!(!is_expired_pat(StatusCode::NOT_FOUND, expired));
+        assert!(!is_expired_pat(
+            StatusCode::UNAUTHORIZED,
+            r#"{"message": "VS30063: You are not authorized to access https://dev.azure.com."}"#
+        ));
+        assert_eq!(
+            client().tokens_web_url(),
+            "https://dev.azure.com/contoso/_usersSettings/tokens"
+        );
+    }
+
+    #[test]
+    fn api_urls_carry_api_version() {
+        assert_eq!(
+            client().git_url(&["repositories"], &[("$top", "1")]),
+            "https://dev.azure.com/contoso/webshop/_apis/git/repositories?api-version=7.1&%24top=1"
+        );
+        assert_eq!(
+            client()
+                .with_api_path("/_apis/preview/")
+                .git_url(&["repositories"], &[]),
+            "https://dev.azure.com/contoso/webshop/_apis/preview/git/repositories?api-version=7.1"
+        );
+    }
+
+    #[test]
+    fn base_url_replaces_dev_azure_com() {
+        let server = client()
+            .with_base_url("http://localhost:8080/tfs/")
+            .unwrap();
+        assert_eq!(
+            server.web_url(&sample_pull_request()),
+            "http://localhost:8080/tfs/contoso/webshop/_git/web%20shop/pullrequest/42"
+        );
+        assert!(client().with_base_url("localhost").is_err());
+    }
+
+    #[test]
+    fn web_url_points_at_pull_request_page() {
+        assert_eq!(
+            client().web_url(&sample_pull_request()),
+            "https://dev.azure.com/contoso/webshop/_git/web%20shop/pullrequest/42"
+        );
+    }
+
+    #[test]
+    fn authors_match_display_name_or_unique_name() {
+        let author = sample_pull_request().created_by;
+        assert!(author.matches_any(&["jane d".into()]));
+        assert!(author.matches_any(&["JANE@contoso".into()]));
+        assert!(!author.matches_any(&["john".into(), "fabrikam.com".into()]));
+        assert!(author.is("Jane@Contoso.com"));
+        assert!(!author.is("jane"));
+    }
+
+    #[test]
+    fn pull_requests_web_url_points_at_overview_page() {
+        assert_eq!(
+            client().pull_requests_web_url(),
+            "https://dev.azure.com/contoso/webshop/_pulls"
+        );
+    }
+
+    #[test]
+    fn source_commit_is_abbreviated() {
+        let mut pr = sample_pull_request();
+        assert_eq!(pr.short_source_commit(), None);
+        pr.last_merge_source_commit =
+            serde_json::from_str(r#"{ "commitId": "8f2a6c1e9b0d4f3a7c5e2b1d0f9a8c7e6b5d4a3f" }"#)
+                .unwrap();
+        assert_eq!(pr.short_source_commit(), Some("8f2a6c1"));
+    }
+
+    #[test]
+    fn retry_after_is_read_as_seconds_or_date() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), DEFAULT_RETRY_AFTER);
+        headers.insert(reqwest::header::RETRY_AFTER, "30".parse().unwrap());
+        assert_eq!(retry_after(&headers), Duration::from_secs(30));
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Duration::ZERO);
+        headers.insert(reqwest::header::RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(retry_after(&headers), DEFAULT_RETRY_AFTER);
+    }
+
+    #[test]
+    fn all_labels_must_be_present() {
+        let mut pr = sample_pull_request();
+        pr.labels =
+            serde_json::from_str(r#"[{ "name": "needs-qa" }, { "name": "Blocked" }]"#).unwrap();
+        assert!(pr.has_labels(&[]));
+        assert!(pr.has_labels(&["blocked".into(), "needs-qa".into()]));
+        assert!(!pr.has_labels(&["needs-qa".into(), "urgent".into()]));
+    }
+
+    #[test]
+    fn votes_are_summarized() {
+        let mut pr = sample_pull_request();
+        assert!(!pr.approvals().is_ready());
+        pr.reviewers = serde_json::from_str(
+            r#"[
+                { "displayName": "Al", "vote": 10 },
+                { "displayName": "Bob", "vote": 5 },
+                { "displayName": "Cy", "vote": 0 },
+                { "displayName": "Di", "vote": -5 }
+            ]"#,
+        )
+        .unwrap();
+        let approvals = pr.approvals();
+        assert_eq!(approvals.to_string(), "2 approved, 1 waiting, 0 rejected");
+        assert!(approvals.is_ready());
+        pr.reviewers[2].vote = -10;
+        assert!(!pr.approvals().is_ready());
+    }
+
+    #[test]
+    fn threads_are_counted_without_system_threads() {
+        let mut pr = sample_pull_request();
+        pr.threads = serde_json::from_str(
+            r#"[
+                { "status": "active", "comments": [{ "commentType": "text" }, { "commentType": "text" }] },
+                { "status": "fixed", "comments": [{ "commentType": "text" }] },
+                { "status": "active", "isDeleted": true, "comments": [{ "commentType": "text" }] },
+                { "comments": [{ "commentType": "system" }] }
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(pr.comment_count(), 3);
+        assert_eq!(pr.unresolved_thread_count(), 1);
+    }
+
+    #[test]
+    fn target_branches_are_given_as_ref_names() {
+        assert_eq!(ref_name("main"), "refs/heads/main");
+        assert_eq!(ref_name("release/1.0"), "refs/heads/release/1.0");
+        assert_eq!(ref_name("refs/heads/main"), "refs/heads/main");
+    }
+
+    #[test]
+    fn branch_names_are_shown_without_refs_heads() {
+        let pr = sample_pull_request();
+        assert_eq!(pr.source_branch(), "feature/widgets");
+        assert_eq!(pr.target_branch(), "main");
+    }
+}
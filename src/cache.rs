@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{PrListError, Result};
+
+const FILE_NAME: &str = "repositories.json";
+const TTL_DAYS: i64 = 7;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Entry {
+    id: String,
+    cached_at: DateTime<Utc>,
+}
+
+/// Repository ids by repository name, kept between runs so that filtering on
+/// a repository doesn't cost an extra request every time. The cache is best
+/// effort: failing to read or write it is only logged.
+pub struct RepositoryCache {
+    path: Option<PathBuf>,
+}
+
+impl RepositoryCache {
+    /// The cache in the prlist directory under the XDG cache home.
+    pub fn new() -> Self {
+        let cache_home = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")));
+        RepositoryCache {
+            path: cache_home.map(|dir| dir.join("prlist").join(FILE_NAME)),
+        }
+    }
+
+    #[cfg(test)]
+    fn at(path: &Path) -> Self {
+        RepositoryCache {
+            path: Some(path.to_path_buf()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        let entries = self.load();
+        let entry = entries.get(key)?;
+        if Utc::now() - entry.cached_at > Duration::days(TTL_DAYS) {
+            debug!("Cached id of {key} has expired");
+            return None;
+        }
+        Some(entry.id.clone())
+    }
+
+    pub fn insert(&self, key: &str, id: &str) {
+        let mut entries = self.load();
+        entries.insert(
+            key.to_string(),
+            Entry {
+                id: id.to_string(),
+                cached_at: Utc::now(),
+            },
+        );
+        self.store(&entries);
+    }
+
+    pub fn remove(&self, key: &str) {
+        let mut entries = self.load();
+        if entries.remove(key).is_some() {
+            self.store(&entries);
+        }
+    }
+
+    fn load(&self) -> BTreeMap<String, Entry> {
+        let Some(path) = &self.path else {
+            return BTreeMap::new();
+        };
+        if !path.is_file() {
+            return BTreeMap::new();
+        }
+        read(path).unwrap_or_else(|error| {
+            debug!("Ignoring repository cache: {error:#}");
+            BTreeMap::new()
+        })
+    }
+
+    fn store(&self, entries: &BTreeMap<String, Entry>) {
+        if let Some(path) = &self.path {
+            if let Err(error) = write(path, entries) {
+                debug!("Could not update repository cache: {error:#}");
+            }
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<BTreeMap<String, Entry>> {
+    let content = std::fs::read_to_string(path).map_err(|source| io_error("read", path, source))?;
+    serde_json::from_str(&content).map_err(|source| PrListError::Decode {
+        what: path.display().to_string(),
+        source,
+    })
+}
+
+fn write(path: &Path, entries: &BTreeMap<String, Entry>) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|source| io_error("create", dir, source))?;
+    }
+    let content = serde_json::to_string(entries).map_err(|source| PrListError::Decode {
+        what: "repository cache".into(),
+        source,
+    })?;
+    std::fs::write(path, content).map_err(|source| io_error("write", path, source))
+}
+
+fn io_error(action: &'static str, path: &Path, source: std::io::Error) -> PrListError {
+    PrListError::Io {
+        action,
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cache(name: &str) -> (RepositoryCache, PathBuf) {
+        let path = std::env::temp_dir()
+            .join(format!("prlist-cache-{}-{name}", std::process::id()))
+            .join(FILE_NAME);
+        (RepositoryCache::at(&path), path)
+    }
+
+    #[test]
+    fn remembers_ids_until_removed() {
+        let (cache, path) = cache("remember");
+        assert_eq!(cache.get("contoso/webshop/shop"), None);
+        cache.insert("contoso/webshop/shop", "1234");
+        assert_eq!(cache.get("contoso/webshop/shop").as_deref(), Some("1234"));
+        cache.remove("contoso/webshop/shop");
+        assert_eq!(cache.get("contoso/webshop/shop"), None);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn expired_ids_are_not_used() {
+        let (cache, path) = cache("expired");
+        let mut entries = BTreeMap::new();
+        entries.insert(
+            "contoso/webshop/shop".to_string(),
+            Entry {
+                id: "1234".into(),
+                cached_at: Utc::now() - Duration::days(TTL_DAYS + 1),
+            },
+        );
+        cache.store(&entries);
+        assert_eq!(cache.get("contoso/webshop/shop"), None);
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+
+pub const FILE_NAME: &str = "prlist.toml";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    pub username: Option<String>,
+    pub organization: Option<String>,
+    pub project: Option<String>,
+    pub pat_file: Option<PathBuf>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// A named set of settings in a `[profiles.<name>]` table, selected with
+/// `--profile`.
+#[derive(Debug, Default, Deserialize)]
+pub struct Profile {
+    pub username: Option<String>,
+    pub organization: Option<String>,
+    pub project: Option<String>,
+    pub pat_file: Option<PathBuf>,
+}
+
+impl Config {
+    /// Loads the first config file found in the current directory or the
+    /// prlist directory under the XDG config home, or an empty config if
+    /// there is none.
+    pub fn load() -> Result<Config> {
+        match search_paths().into_iter().find(|path| path.is_file()) {
+            Some(path) => Config::read(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Replaces the settings with those in the profile `name`, keeping the
+    /// ones the profile doesn't set.
+    pub fn with_profile(mut self, name: &str) -> Result<Config> {
+        let profile = self.profiles.remove(name).ok_or_else(|| {
+            let names: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            anyhow!(
+                "There is no profile {name} in the config file, the profiles are: {}",
+                names.join(", ")
+            )
+        })?;
+        Ok(Config {
+            username: profile.username.or(self.username),
+            organization: profile.organization.or(self.organization),
+            project: profile.project.or(self.project),
+            pat_file: profile.pat_file.or(self.pat_file),
+            profiles: BTreeMap::new(),
+        })
+    }
+
+    fn read(path: &Path) -> Result<Config> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))
+    }
+}
+
+fn search_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(FILE_NAME)];
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(config_home) = config_home {
+        paths.push(config_home.join("prlist").join(FILE_NAME));
+    }
+    paths
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn all_keys_are_optional() {
+        let config: Config = toml::from_str("organization = \"contoso\"").unwrap();
+        assert_eq!(config.organization.as_deref(), Some("contoso"));
+        assert_eq!(config.username, None);
+        assert_eq!(config.project, None);
+        assert_eq!(config.pat_file, None);
+    }
+
+    #[test]
+    fn reads_all_keys() {
+        let config: Config = toml::from_str(
+            r#"
+username = "jane@contoso.com"
+organization = "contoso"
+project = "webshop"
+pat_file = "/home/jane/.azure-pat"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.username.as_deref(), Some("jane@contoso.com"));
+        assert_eq!(config.organization.as_deref(), Some("contoso"));
+        assert_eq!(config.project.as_deref(), Some("webshop"));
+        assert_eq!(
+            config.pat_file,
+            Some(PathBuf::from("/home/jane/.azure-pat"))
+        );
+    }
+
+    #[test]
+    fn profile_overrides_top_level_settings() {
+        let config: Config = toml::from_str(
+            r#"
+username = "jane@contoso.com"
+organization = "contoso"
+
+[profiles.fabrikam]
+organization = "fabrikam"
+project = "rockets"
+pat_file = "/home/jane/.fabrikam-pat"
+"#,
+        )
+        .unwrap();
+        let config = config.with_profile("fabrikam").unwrap();
+        assert_eq!(config.username.as_deref(), Some("jane@contoso.com"));
+        assert_eq!(config.organization.as_deref(), Some("fabrikam"));
+        assert_eq!(config.project.as_deref(), Some("rockets"));
+        assert_eq!(
+            config.pat_file,
+            Some(PathBuf::from("/home/jane/.fabrikam-pat"))
+        );
+    }
+
+    #[test]
+    fn unknown_profile_is_an_error() {
+        let config: Config = toml::from_str("[profiles.work]\nproject = \"webshop\"").unwrap();
+        let error = config.with_profile("home").unwrap_err().to_string();
+        assert!(error.contains("home"));
+        assert!(error.contains("work"));
+    }
+}
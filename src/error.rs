@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+
+use reqwest::StatusCode;
+use serde::Deserialize;
+
+pub type Result<T, E = PrListError> = std::result::Result<T, E>;
+
+/// The ways fetching pull requests can fail, for callers that want to handle
+/// some of them differently.
+#[derive(Debug, thiserror::Error)]
+pub enum PrListError {
+    /// Azure DevOps didn't accept the credentials.
+    #[error("Azure DevOps refused access to {what} at {url} ({status}), check {credentials}")]
+    Auth {
+        what: String,
+        url: String,
+        status: StatusCode,
+        /// What was sent, like "the username and PAT".
+        credentials: &'static str,
+    },
+    /// Azure DevOps said the PAT has expired.
+    #[error("Azure DevOps refused access to {what} at {url}. Your PAT may be expired; regenerate it at {tokens_url}")]
+    ExpiredPat {
+        what: String,
+        url: String,
+        tokens_url: String,
+    },
+    /// The request couldn't be sent or the reply couldn't be received.
+    #[error("Failed to get {what} from {url}")]
+    Network {
+        what: String,
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// There was no reply within the timeout given to
+    /// [http_client](crate::azure::http_client).
+    #[error("Timed out getting {what} from {url}, see --timeout")]
+    Timeout { what: String, url: String },
+    /// Azure DevOps replied with an error payload.
+    #[error("Failed to get {what} from {url}")]
+    Api {
+        what: String,
+        url: String,
+        status: StatusCode,
+        #[source]
+        source: ApiError,
+    },
+    /// Azure DevOps replied with an unsuccessful status and no error payload.
+    #[error("Failed to get {what} from {url}: {status}\n{body}")]
+    Status {
+        what: String,
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// A reply or a file didn't contain what was expected.
+    #[error("Failed to parse {what}")]
+    Decode {
+        what: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// A file couldn't be read or written.
+    #[error("Failed to {action} {}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    /// The Azure CLI couldn't give an access token.
+    #[error("{message}")]
+    AzureCli {
+        message: String,
+        #[source]
+        source: Option<std::io::Error>,
+    },
+    /// The settings can't be used, like a malformed proxy URL.
+    #[error("{message}")]
+    Config {
+        message: String,
+        #[source]
+        source: Option<reqwest::Error>,
+    },
+}
+
+/// The error Azure DevOps replies with when it can't do what was asked, e.g.
+/// because a repository doesn't exist.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiError {
+    pub message: String,
+    /// The kind of error, like `GitRepositoryNotFoundException`.
+    pub type_key: Option<String>,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Azure DevOps replied: {}", self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
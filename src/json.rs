@@ -0,0 +1,85 @@
+use chrono::{DateTime, Utc};
+use prlist::azure::{CommitRef, Label, ResourceRef, Thread};
+use prlist::{AzureClient, Person, PullRequest, Repository, Reviewer};
+use serde::Serialize;
+
+/// A pull request as printed by `--format json` and `--format ndjson`, with
+/// the fields always in this order. The names are the ones Azure DevOps
+/// uses, so the output can be read back with `--from-file`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonPullRequest<'a> {
+    pub pull_request_id: u32,
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+    pub is_draft: bool,
+    pub status: &'a str,
+    pub created_by: &'a Person,
+    pub creation_date: DateTime<Utc>,
+    pub closed_date: Option<DateTime<Utc>>,
+    pub repository: &'a Repository,
+    pub source_ref_name: &'a str,
+    pub target_ref_name: &'a str,
+    pub last_merge_source_commit: Option<&'a CommitRef>,
+    pub merge_status: Option<&'a str>,
+    pub labels: &'a [Label],
+    pub reviewers: &'a [Reviewer],
+    pub work_item_refs: &'a [ResourceRef],
+    pub threads: &'a [Thread],
+    /// The page of the pull request in the Azure DevOps web UI.
+    pub url: String,
+}
+
+impl<'a> JsonPullRequest<'a> {
+    pub fn new(pr: &'a PullRequest, client: &AzureClient) -> Self {
+        JsonPullRequest {
+            pull_request_id: pr.pull_request_id,
+            title: &pr.title,
+            description: pr.description.as_deref(),
+            is_draft: pr.is_draft,
+            status: &pr.status,
+            created_by: &pr.created_by,
+            creation_date: pr.creation_date,
+            closed_date: pr.closed_date,
+            repository: &pr.repository,
+            source_ref_name: &pr.source_ref_name,
+            target_ref_name: &pr.target_ref_name,
+            last_merge_source_commit: pr.last_merge_source_commit.as_ref(),
+            merge_status: pr.merge_status.as_deref(),
+            labels: &pr.labels,
+            reviewers: &pr.reviewers,
+            work_item_refs: &pr.work_item_refs,
+            threads: &pr.threads,
+            url: client.web_url(pr),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::pull_request;
+    use prlist::azure::Credentials;
+
+    #[test]
+    fn fields_are_in_a_fixed_order_and_can_be_read_back() {
+        let pr = pull_request("Add widgets", "Adds widgets.");
+        let client = AzureClient::new(
+            reqwest::Client::new(),
+            "contoso",
+            "webshop",
+            Credentials::Pat {
+                username: String::new(),
+                pat: String::new(),
+            },
+            "7.1",
+        );
+        let json = serde_json::to_string(&JsonPullRequest::new(&pr, &client)).unwrap();
+        assert_eq!(
+            json,
+            r#"{"pullRequestId":1,"title":"Add widgets","description":"Adds widgets.","isDraft":false,"status":"active","createdBy":{"displayName":"Mallory","uniqueName":""},"creationDate":"2022-11-20T10:15:00Z","closedDate":null,"repository":{"id":"1","name":"repo"},"sourceRefName":"refs/heads/evil","targetRefName":"refs/heads/main","lastMergeSourceCommit":null,"mergeStatus":null,"labels":[],"reviewers":[],"workItemRefs":[],"threads":[],"url":"https://dev.azure.com/contoso/webshop/_git/repo/pullrequest/1"}"#
+        );
+        let read_back: PullRequest = serde_json::from_str(&json).unwrap();
+        assert_eq!(read_back.pull_request_id, 1);
+    }
+}
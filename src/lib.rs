@@ -0,0 +1,23 @@
+//! Listing of pull requests in Azure DevOps projects, along with a parser
+//! for the markdown-ish text in their descriptions.
+
+pub mod azure;
+mod cache;
+mod error;
+pub mod text;
+
+pub use azure::{
+    Approvals, AzureClient, Person, PullRequest, PullRequestQuery, Repository, Reviewer,
+};
+pub use error::{ApiError, PrListError, Result};
+
+/// Fetches the pull requests matching `query`, including the full
+/// descriptions of those the list endpoint truncated.
+pub async fn fetch_pull_requests(
+    client: &AzureClient,
+    query: &PullRequestQuery<'_>,
+) -> Result<Vec<PullRequest>> {
+    let mut pull_requests = client.list_pull_requests(query).await?;
+    client.complete_descriptions(&mut pull_requests).await?;
+    Ok(pull_requests)
+}
@@ -1,95 +1,2087 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use early::Early;
-use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
-mod text;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::{CommandFactory, Parser};
+use json::JsonPullRequest;
+use owo_colors::{OwoColorize, Style};
+use prlist::{azure, text, AzureClient, PrListError, PullRequest, PullRequestQuery};
 
-#[derive(Debug, Deserialize)]
-struct Reply<T> {
-    value: Vec<T>,
+mod config;
+mod json;
+mod template;
+
+const DEFAULT_WIDTH: usize = 70;
+const MAX_OPENED_PULL_REQUESTS: usize = 5;
+
+const EXIT_FAILURE: u8 = 1;
+const EXIT_AUTH: u8 = 3;
+const EXIT_NETWORK: u8 = 4;
+const EXIT_API: u8 = 5;
+const EXIT_STATUS_HELP: &str = "\
+Exit status:
+  0  Success
+  1  Other errors, or open pull requests with --fail-on-open
+  2  Invalid arguments
+  3  Azure DevOps refused the credentials, or the Azure CLI couldn't get a token
+  4  Network errors and timeouts
+  5  Azure DevOps replied with an error";
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+    /// One JSON object per line
+    Ndjson,
+    /// A section per pull request, e.g. for release notes
+    Markdown,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Auto enables colors when writing to a terminal, unless the NO_COLOR
+    /// environment variable is set.
+    fn enabled(self, terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => terminal && std::env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct Person {}
+struct Styles {
+    header: Style,
+    author: Style,
+    id: Style,
+    draft: Style,
+    conflicts: Style,
+    bold: Style,
+    italic: Style,
+    /// How to show code spans, or `None` to keep their backticks.
+    code: Option<Style>,
+    hyperlinks: bool,
+    /// Whether to replace shortcodes like `:rocket:` with emoji.
+    emoji: bool,
+}
+
+impl Styles {
+    fn new(color: bool) -> Self {
+        if color {
+            Styles {
+                header: Style::new().bold(),
+                author: Style::new().cyan(),
+                id: Style::new().green(),
+                draft: Style::new().yellow(),
+                conflicts: Style::new().red(),
+                bold: Style::new().bold(),
+                italic: Style::new().italic(),
+                code: Some(Style::new().magenta()),
+                hyperlinks: true,
+                emoji: false,
+            }
+        } else {
+            Styles {
+                header: Style::new(),
+                author: Style::new(),
+                id: Style::new(),
+                draft: Style::new(),
+                conflicts: Style::new(),
+                bold: Style::new(),
+                italic: Style::new(),
+                code: None,
+                hyperlinks: false,
+                emoji: false,
+            }
+        }
+    }
+}
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct PullRequest {
-    title: String,
-    description: Option<String>,
-    is_draft: bool,
-    pull_request_id: u32,
-    created_by: Author,
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum StatusFilter {
+    Active,
+    Completed,
+    Abandoned,
+    All,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct Author {
-    display_name: String,
+impl StatusFilter {
+    fn query_value(self) -> &'static str {
+        match self {
+            StatusFilter::Active => "active",
+            StatusFilter::Completed => "completed",
+            StatusFilter::Abandoned => "abandoned",
+            StatusFilter::All => "all",
+        }
+    }
+
+    fn matches(self, status: &str) -> bool {
+        matches!(self, StatusFilter::All) || self.query_value().eq_ignore_ascii_case(status)
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            StatusFilter::Active => "open",
+            StatusFilter::Completed => "completed",
+            StatusFilter::Abandoned => "abandoned",
+            StatusFilter::All => "matching",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+enum Field {
+    Id,
+    Author,
+    Title,
+    Url,
+    Repository,
+    Branch,
+    Date,
+    Status,
+    Labels,
+    Comments,
+    Changes,
+    Approvals,
+    Reviewers,
+    WorkItems,
+    Description,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum GroupBy {
+    /// Target branch
+    #[value(alias = "target")]
+    Branch,
+    /// Author of the pull request
+    Author,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum SortKey {
+    Id,
+    Author,
+    Title,
+    /// Creation date, newest first
+    Date,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum AuthMethod {
+    /// The PAT in the PAT file
+    Pat,
+    /// An access token from the Azure CLI, logged in with az login
+    AzCli,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// List the repositories in the project with their ids
+    Repos,
 }
 
 #[derive(clap::Parser)]
+#[command(after_help = EXIT_STATUS_HELP)]
 struct Options {
-    /// Path to the file containing the PAT for authenticating with Azure DevOps
-    pat_file: std::path::PathBuf,
+    /// Path to the file containing the PAT for authenticating with Azure DevOps.
+    /// Leave it out with --pat, --auth az-cli or --from-file
+    pat_file: Option<PathBuf>,
     /// Username on Azure DevOps
-    username: String,
+    username: Option<String>,
     /// Name of the Azure DevOps organization
+    organization: Option<String>,
+    /// Name of the team project in Azure DevOps, or a comma-separated list of projects
+    project: Option<String>,
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Maximum number of pull requests to fetch
+    #[arg(long)]
+    max: Option<usize>,
+    /// Only show the first pull requests, after filtering and sorting
+    #[arg(long, alias = "top")]
+    limit: Option<usize>,
+    /// Include draft pull requests
+    #[arg(long)]
+    include_drafts: bool,
+    /// Only show pull requests created by this author, matching part of the name or email (case-insensitive, repeatable)
+    #[arg(long = "author")]
+    authors: Vec<String>,
+    /// Hide pull requests created by this author, matching like --author (repeatable)
+    #[arg(long = "exclude-author")]
+    excluded_authors: Vec<String>,
+    /// Only show pull requests created by the configured user
+    #[arg(long)]
+    me: bool,
+    /// Only show pull requests with this label (repeatable, all must match)
+    #[arg(long = "label")]
+    labels: Vec<String>,
+    /// Order in which pull requests are listed
+    #[arg(long, value_enum, default_value_t = SortKey::Id)]
+    sort: SortKey,
+    /// Reverse the sort order
+    #[arg(long)]
+    reverse: bool,
+    /// Only show pull requests in this repository
+    #[arg(long)]
+    repository: Option<String>,
+    /// Only show pull requests merging into this branch, e.g. main
+    #[arg(long)]
+    target: Option<String>,
+    /// When to use colors in the output. Auto disables them if NO_COLOR is set
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+    /// Only show pull requests created, or for closed ones closed, within this duration (e.g. 7d, 24h, 2w)
+    #[arg(long, value_parser = parse_duration)]
+    since: Option<Duration>,
+    /// Only show pull requests created at or after this date or time (e.g. 2024-05-01, UTC)
+    #[arg(long, value_parser = parse_date)]
+    created_after: Option<DateTime<Utc>>,
+    /// Only show pull requests created before this date or time (e.g. 2024-05-15T12:00:00Z)
+    #[arg(long, value_parser = parse_date)]
+    created_before: Option<DateTime<Utc>>,
+    /// Exit with a non-zero status if any non-draft pull request is found
+    #[arg(long)]
+    fail_on_open: bool,
+    /// Only show pull requests with this status
+    #[arg(long, value_enum, default_value_t = StatusFilter::Active)]
+    status: StatusFilter,
+    /// Only show pull requests that someone approved and no one rejected
+    #[arg(long)]
+    ready: bool,
+    /// Only show pull requests where you are a reviewer who has not voted yet
+    #[arg(long)]
+    awaiting_my_review: bool,
+    /// Width to wrap descriptions at [default: terminal width or 70]
+    #[arg(long)]
+    width: Option<usize>,
+    /// Number of spaces to indent the lines under each title with
+    #[arg(long, default_value_t = 3)]
+    indent: usize,
+    /// PAT to use instead of the one in the PAT file. Insecure, as it can leak
+    /// through the shell history and the process list; only use it for quick tests
+    #[arg(long)]
+    pat: Option<String>,
+    /// How to authenticate with Azure DevOps. With az-cli, leave out the PAT file argument
+    #[arg(long, value_enum, default_value_t = AuthMethod::Pat)]
+    auth: AuthMethod,
+    /// Version of the Azure DevOps REST API to use
+    #[arg(long, default_value = "7.1")]
+    api_version: String,
+    /// Server to connect to, e.g. an Azure DevOps Server
+    #[arg(long, default_value = "https://dev.azure.com")]
+    base_url: String,
+    /// Path of the REST API in the project, e.g. for previews of new endpoints
+    #[arg(long, default_value = azure::DEFAULT_API_PATH)]
+    api_path: String,
+    /// Don't print informational messages or warnings, only the pull requests and errors
+    #[arg(short, long)]
+    quiet: bool,
+    /// Log requests and their timing to stderr
+    #[arg(short, long)]
+    verbose: bool,
+    /// Replace shortcodes like :rocket: in descriptions with emoji
+    #[arg(long)]
+    emoji: bool,
+    /// Don't show the descriptions of the pull requests
+    #[arg(long)]
+    no_description: bool,
+    /// Don't fetch the work items linked to each pull request
+    #[arg(long)]
+    no_work_items: bool,
+    /// Fetch the comment threads of each pull request and show how many comments there are
+    #[arg(long)]
+    threads: bool,
+    /// Only show pull requests with unresolved comment threads, fetching the threads
+    #[arg(long, conflicts_with = "all_resolved")]
+    has_unresolved: bool,
+    /// Only show pull requests with all comment threads resolved, fetching the threads
+    #[arg(long)]
+    all_resolved: bool,
+    /// Fetch and show how many files each pull request changes
+    #[arg(long)]
+    diff_stats: bool,
+    /// Comma-separated fields to show for each pull request, in order. Reviewers,
+    /// work-items and description are printed below a line with the other fields
+    #[arg(long, value_enum, value_delimiter = ',')]
+    fields: Vec<Field>,
+    /// Print the pull requests in groups
+    #[arg(long, value_enum)]
+    group_by: Option<GroupBy>,
+    /// Read the pull requests from a JSON file instead of Azure DevOps
+    #[arg(long)]
+    from_file: Option<PathBuf>,
+    /// Only print the number of pull requests
+    #[arg(long)]
+    count_only: bool,
+    /// Only print how many pull requests there are per author or target branch, most first
+    #[arg(long, value_enum, conflicts_with_all = ["count_only", "oneline", "template", "format"])]
+    count_by: Option<GroupBy>,
+    /// Print a completion script for the given shell and exit
+    #[arg(long, value_enum, hide = true)]
+    generate_completions: Option<clap_complete::Shell>,
+    /// Only show pull requests with merge conflicts
+    #[arg(long)]
+    conflicts_only: bool,
+    /// Keep refreshing the list until interrupted
+    #[arg(long, conflicts_with = "fail_on_open")]
+    watch: bool,
+    /// Seconds between refreshes in watch mode
+    #[arg(long, default_value_t = 60, requires = "watch")]
+    interval: u64,
+    /// Only show the pull request with this id, regardless of the other filters
+    #[arg(long)]
+    id: Option<u32>,
+    /// Start with a line counting all fetched pull requests, before filtering
+    #[arg(long)]
+    summary: bool,
+    /// Proxy to connect through [default: from HTTPS_PROXY or HTTP_PROXY]
+    #[arg(long)]
+    proxy: Option<String>,
+    /// Don't verify TLS certificates, e.g. behind an intercepting proxy
+    #[arg(long)]
+    insecure: bool,
+    /// Write the output to this file instead of stdout
+    #[arg(long, short, conflicts_with = "watch")]
+    output: Option<PathBuf>,
+    /// Seconds to wait for each request to Azure DevOps
+    #[arg(long, default_value_t = 30)]
+    timeout: u64,
+    /// Print a line per pull request like "{id} {author}: {title} ({branch})". Placeholders:
+    /// id, author, title, url, branch, source, target, date, status, repository, reviewers,
+    /// work_items
+    #[arg(long, value_parser = template::Template::parse, conflicts_with_all = ["format", "fields", "group_by", "summary"])]
+    template: Option<template::Template>,
+    /// Print a single line per pull request like "#42 Jane Doe: Add widgets"
+    #[arg(long, conflicts_with_all = ["format", "fields", "group_by", "template"])]
+    oneline: bool,
+    /// Indent the JSON output
+    #[arg(long)]
+    pretty: bool,
+    /// Use the settings in this profile of the config file
+    #[arg(long)]
+    profile: Option<String>,
+    /// Open the pull requests in the browser, or the overview page if there are more than five
+    #[arg(long, conflicts_with = "watch")]
+    open: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Options {
+    /// Whether the comment threads are shown, by --threads or --fields comments.
+    fn shows_threads(&self) -> bool {
+        self.threads || self.fields.contains(&Field::Comments)
+    }
+
+    /// Reads the positional arguments as the username, organization and
+    /// project when no PAT file is needed and not all four are given.
+    fn without_pat_file(mut self) -> Self {
+        let pat_file_needed =
+            self.pat.is_none() && self.from_file.is_none() && matches!(self.auth, AuthMethod::Pat);
+        if !pat_file_needed && self.project.is_none() {
+            self.project = self.organization.take();
+            self.organization = self.username.take();
+            self.username = self
+                .pat_file
+                .take()
+                .map(|path| path.to_string_lossy().into_owned());
+        }
+        self
+    }
+}
+
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim().to_lowercase();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (amount, unit) = s.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("'{s}' does not start with a number"))?;
+    let duration = match unit.trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => Duration::try_seconds(amount),
+        "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(amount),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Duration::try_hours(amount),
+        "" | "d" | "day" | "days" => Duration::try_days(amount),
+        "w" | "wk" | "wks" | "week" | "weeks" => Duration::try_weeks(amount),
+        unit => return Err(format!("unknown duration unit '{unit}'")),
+    };
+    duration.ok_or_else(|| format!("'{s}' is too long a duration"))
+}
+
+/// Parses an ISO 8601 date, meaning midnight UTC, or date and time.
+fn parse_date(s: &str) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+    DateTime::parse_from_rfc3339(s)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| {
+            format!("'{s}' is not a date like 2024-05-01 or a time like 2024-05-01T12:00:00Z")
+        })
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        // The reader went away, like `head` does after enough lines, so
+        // there is no one left to tell.
+        Err(error) if is_broken_pipe(&error) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            ExitCode::from(exit_code(&error))
+        }
+    }
+}
+
+/// The exit status for `error`, so that scripts can tell failures that are
+/// worth retrying from those that aren't.
+fn exit_code(error: &anyhow::Error) -> u8 {
+    let error = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<PrListError>());
+    match error {
+        Some(PrListError::Auth { .. } | PrListError::ExpiredPat { .. }) => EXIT_AUTH,
+        Some(PrListError::AzureCli { .. }) => EXIT_AUTH,
+        Some(PrListError::Network { .. } | PrListError::Timeout { .. }) => EXIT_NETWORK,
+        Some(PrListError::Api { .. } | PrListError::Status { .. }) => EXIT_API,
+        _ => EXIT_FAILURE,
+    }
+}
+
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let kind = match cause.downcast_ref::<serde_json::Error>() {
+            Some(error) => error.io_error_kind(),
+            None => cause
+                .downcast_ref::<std::io::Error>()
+                .map(|error| error.kind()),
+        };
+        kind == Some(std::io::ErrorKind::BrokenPipe)
+    })
+}
+
+async fn run() -> Result<()> {
+    let options = Options::parse().without_pat_file();
+    if let Some(shell) = options.generate_completions {
+        let mut command = Options::command();
+        // Generated into a buffer as clap_complete panics on write errors,
+        // such as a closed pipe.
+        let mut script = Vec::new();
+        clap_complete::generate(shell, &mut command, "prlist", &mut script);
+        std::io::stdout().write_all(&script)?;
+        return Ok(());
+    }
+    env_logger::Builder::new()
+        .filter_module(
+            module_path!(),
+            if options.verbose {
+                log::LevelFilter::Debug
+            } else if options.quiet {
+                log::LevelFilter::Error
+            } else {
+                log::LevelFilter::Warn
+            },
+        )
+        .init();
+
+    let config = match &options.profile {
+        Some(profile) => config::Config::load()?.with_profile(profile)?,
+        None => config::Config::load()?,
+    };
+    let organization = required(
+        options.organization.clone(),
+        config.organization,
+        "organization",
+    )?;
+    let projects = required(options.project.clone(), config.project, "project")?;
+    let projects: Vec<String> = projects
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect();
+    if options.id.is_some() && projects.len() > 1 {
+        bail!("--id can only be used with a single project");
+    }
+    if let (Some(_), AuthMethod::AzCli) = (&options.pat, options.auth) {
+        bail!("--pat can't be used with --auth az-cli");
+    }
+    let (username, credentials) = match (&options.from_file, options.auth) {
+        (Some(_), _) => {
+            if projects.len() > 1 {
+                bail!("--from-file can only be used with a single project");
+            }
+            let username = options.username.clone().or(config.username);
+            let credentials = azure::Credentials::Pat {
+                username: String::new(),
+                pat: String::new(),
+            };
+            (username.unwrap_or_default(), credentials)
+        }
+        (None, AuthMethod::Pat) => {
+            let username = required(options.username.clone(), config.username, "username")?;
+            let pat = match &options.pat {
+                Some(pat) => {
+                    if !options.quiet {
+                        eprintln!(
+                            "WARNING: A PAT given with --pat can leak through the shell history \
+                         and the process list. Put it in a PAT file instead."
+                        );
+                    }
+                    pat.trim().to_string()
+                }
+                None => {
+                    let pat_file = required(options.pat_file.clone(), config.pat_file, "pat_file")?;
+                    read_pat(&pat_file)?
+                }
+            };
+            let credentials = azure::Credentials::Pat {
+                username: username.clone(),
+                pat,
+            };
+            (username, credentials)
+        }
+        (None, AuthMethod::AzCli) => {
+            let username = options.username.clone().or(config.username);
+            (
+                username.unwrap_or_default(),
+                azure::Credentials::Bearer(azure::az_cli_token()?),
+            )
+        }
+    };
+
+    if (options.me || options.awaiting_my_review) && username.is_empty() {
+        bail!("--me and --awaiting-my-review need a username");
+    }
+    if options.insecure && !options.quiet {
+        eprintln!(
+            "WARNING: TLS certificate verification is disabled by --insecure. \
+             Anyone on the network path can read your PAT and fake the replies."
+        );
+    }
+    let connection = Connection {
+        http: azure::http_client(
+            options.proxy.as_deref(),
+            options.insecure,
+            std::time::Duration::from_secs(options.timeout),
+        )?,
+        organization,
+        projects,
+        username,
+        credentials,
+    };
+
+    if let Some(Command::Repos) = options.command {
+        if options.from_file.is_some() {
+            bail!("The repos command can't be used with --from-file");
+        }
+        return list_repositories(&options, &connection).await;
+    }
+    if options.watch {
+        return watch(&options, &connection).await;
+    }
+    let groups = fetch(&options, &connection).await?;
+    match &options.output {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            let mut out = BufWriter::new(file);
+            print(&mut out, &groups, &options)
+                .and_then(|()| Ok(out.flush()?))
+                .with_context(|| format!("Failed to write to {}", path.display()))?;
+        }
+        None => print(&mut std::io::stdout().lock(), &groups, &options)?,
+    }
+    if options.open {
+        open_in_browser(&groups)?;
+    }
+
+    if options.fail_on_open {
+        let total = groups.iter().map(|g| g.pull_requests.len()).sum::<usize>();
+        if !options.quiet {
+            eprintln!("{total} pull request(s) found");
+        }
+        if groups
+            .iter()
+            .flat_map(|g| &g.pull_requests)
+            .any(|pr| !pr.is_draft)
+        {
+            std::process::exit(EXIT_FAILURE.into());
+        }
+    }
+    Ok(())
+}
+
+/// Fetches and prints the pull requests every `--interval` seconds, clearing
+/// the screen in between, until interrupted with Ctrl-C.
+async fn watch(options: &Options, connection: &Connection) -> Result<()> {
+    let interval = std::time::Duration::from_secs(options.interval);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut resizes = Resizes::new()?;
+    loop {
+        let groups = tokio::select! {
+            groups = fetch(options, connection) => groups,
+            result = &mut ctrl_c => return Ok(result?),
+        };
+        redraw(&groups, options)?;
+        let next_refresh = tokio::time::sleep(interval);
+        tokio::pin!(next_refresh);
+        loop {
+            tokio::select! {
+                _ = &mut next_refresh => break,
+                _ = resizes.next() => redraw(&groups, options)?,
+                result = &mut ctrl_c => return Ok(result?),
+            }
+        }
+    }
+}
+
+/// Clears the terminal and prints the pull requests, or why they couldn't be
+/// fetched. The width to wrap at is detected again each time.
+fn redraw(groups: &Result<Vec<ProjectPullRequests>>, options: &Options) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b[2J\x1b[H")?;
+    match groups {
+        Ok(groups) => print(&mut stdout, groups, options)?,
+        Err(error) => eprintln!("Error: {error:#}"),
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Resizes of the terminal, signalled by SIGWINCH. There are none on
+/// platforms without it.
+struct Resizes {
+    #[cfg(unix)]
+    signal: tokio::signal::unix::Signal,
+}
+
+impl Resizes {
+    fn new() -> Result<Self> {
+        Ok(Resizes {
+            #[cfg(unix)]
+            signal: tokio::signal::unix::signal(tokio::signal::unix::SignalKind::window_change())
+                .context("Failed to listen for terminal resizes")?,
+        })
+    }
+
+    async fn next(&mut self) {
+        #[cfg(unix)]
+        if self.signal.recv().await.is_some() {
+            return;
+        }
+        std::future::pending().await
+    }
+}
+
+/// Prints the name and id of each repository, under a header per project if
+/// there are several.
+async fn list_repositories(options: &Options, connection: &Connection) -> Result<()> {
+    let mut out = std::io::stdout().lock();
+    for (i, project) in connection.projects.iter().enumerate() {
+        let client = connection.client(project, options)?;
+        let mut repositories = client
+            .repositories()
+            .await
+            .with_context(|| format!("Could not list the repositories in {project}"))?;
+        repositories.sort_by_key(|repository| repository.name.to_lowercase());
+        if connection.projects.len() > 1 {
+            if i > 0 {
+                writeln!(out)?;
+            }
+            writeln!(out, "{project}:")?;
+        }
+        let width = repositories.iter().map(|r| r.name.chars().count()).max();
+        for repository in &repositories {
+            writeln!(
+                out,
+                "{:width$}  {}",
+                repository.name,
+                repository.id,
+                width = width.unwrap_or(0)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+async fn fetch(options: &Options, connection: &Connection) -> Result<Vec<ProjectPullRequests>> {
+    let username = connection.username.as_str();
+    let mut groups: Vec<ProjectPullRequests> = vec![];
+    for project in &connection.projects {
+        let client = connection.client(project, options)?;
+        let pull_requests = match (&options.from_file, options.id) {
+            (Some(path), _) => {
+                let mut pull_requests = azure::read_pull_requests(path)?;
+                if let Some(max) = options.max {
+                    pull_requests.truncate(max);
+                }
+                pull_requests
+            }
+            (None, Some(id)) => vec![client
+                .pull_request(id)
+                .await
+                .with_context(|| format!("Could not get pull request {id}"))?],
+            (None, None) => {
+                client
+                    .list_pull_requests(&PullRequestQuery {
+                        repository: options.repository.as_deref(),
+                        status: options.status.query_value(),
+                        target: options.target.as_deref(),
+                        max: options.max,
+                    })
+                    .await?
+            }
+        };
+        let summary = Summary::new(&pull_requests, username);
+        let mut pull_requests = match options.id {
+            Some(id) => {
+                let pull_requests: Vec<PullRequest> = pull_requests
+                    .into_iter()
+                    .filter(|pr| pr.pull_request_id == id)
+                    .collect();
+                if pull_requests.is_empty() {
+                    bail!("There is no pull request with id {id}");
+                }
+                pull_requests
+            }
+            None => select(pull_requests, options, username),
+        };
+        let filter_threads = options.has_unresolved || options.all_resolved;
+        if filter_threads {
+            if options.from_file.is_none() {
+                client.fetch_threads(&mut pull_requests).await?;
+            }
+            pull_requests.retain(|pr| matches_thread_filter(pr, options));
+        }
+        if let (Some(limit), None) = (options.limit, options.id) {
+            let shown: usize = groups.iter().map(|g| g.pull_requests.len()).sum();
+            pull_requests.truncate(limit.saturating_sub(shown));
+        }
+        if options.from_file.is_none()
+            && !options.count_only
+            && !options.oneline
+            && options.count_by.is_none()
+        {
+            if options.id.is_none() && !options.no_description {
+                client.complete_descriptions(&mut pull_requests).await?;
+            }
+            if !options.no_work_items {
+                client.fetch_work_item_refs(&mut pull_requests).await?;
+            }
+            if options.shows_threads() && !filter_threads {
+                client.fetch_threads(&mut pull_requests).await?;
+            }
+            if options.diff_stats {
+                client.fetch_change_counts(&mut pull_requests).await?;
+            }
+        }
+        groups.push(ProjectPullRequests {
+            project: project.clone(),
+            client,
+            summary,
+            pull_requests,
+        });
+    }
+    Ok(groups)
+}
+
+fn print(out: &mut dyn Write, groups: &[ProjectPullRequests], options: &Options) -> Result<()> {
+    let terminal = options.output.is_none() && std::io::stdout().is_terminal();
+    let total = groups.iter().map(|g| g.pull_requests.len()).sum::<usize>();
+    match (options.format, &options.template) {
+        _ if options.count_only => writeln!(out, "{total}")?,
+        _ if options.count_by.is_some() => print_counts(out, groups, options)?,
+        _ if options.oneline => {
+            let styles = Styles::new(options.color.enabled(terminal));
+            for group in groups {
+                for pr in &group.pull_requests {
+                    writeln!(
+                        out,
+                        "{} {}: {}",
+                        format!("#{}", pr.pull_request_id).style(styles.id),
+                        text::sanitize(&pr.created_by.display_name).style(styles.author),
+                        title(pr, &group.client.web_url(pr), &styles)
+                    )?;
+                }
+            }
+        }
+        (OutputFormat::Text, Some(template)) => {
+            for group in groups {
+                for pr in &group.pull_requests {
+                    writeln!(out, "{}", template.render(pr, &group.client.web_url(pr)))?;
+                }
+            }
+        }
+        (OutputFormat::Text, None) => {
+            let width = options
+                .width
+                .or_else(|| {
+                    terminal
+                        .then(terminal_size::terminal_size)
+                        .flatten()
+                        .map(|(width, _)| width.0 as usize)
+                })
+                .unwrap_or(DEFAULT_WIDTH);
+            let layout = Layout {
+                width,
+                indent: " ".repeat(options.indent),
+            };
+            let styles = Styles {
+                emoji: options.emoji,
+                ..Styles::new(options.color.enabled(terminal))
+            };
+            if options.summary {
+                let mut summary = Summary::default();
+                for group in groups {
+                    summary.add(&group.summary);
+                }
+                writeln!(out, "{}", summary.line(options.status).style(styles.header))?;
+                writeln!(out)?;
+            }
+            for group in groups {
+                if groups.len() > 1 {
+                    writeln!(
+                        out,
+                        "{}",
+                        format!("== {} ==", group.project).style(styles.header)
+                    )?;
+                    writeln!(out)?;
+                }
+                match options.group_by {
+                    Some(group_by) => {
+                        for (name, members) in group_pull_requests(&group.pull_requests, group_by) {
+                            writeln!(
+                                out,
+                                "{}",
+                                format!("{name} ({})", members.len()).style(styles.header)
+                            )?;
+                            writeln!(out)?;
+                            print_text(out, members, &styles, &layout, &group.client, options)?;
+                        }
+                    }
+                    None => print_text(
+                        out,
+                        &group.pull_requests,
+                        &styles,
+                        &layout,
+                        &group.client,
+                        options,
+                    )?,
+                }
+            }
+        }
+        (OutputFormat::Json, _) => {
+            let pull_requests: Vec<JsonPullRequest> = groups
+                .iter()
+                .flat_map(|g| {
+                    g.pull_requests
+                        .iter()
+                        .map(|pr| JsonPullRequest::new(pr, &g.client))
+                })
+                .collect();
+            if options.pretty {
+                writeln!(out, "{}", serde_json::to_string_pretty(&pull_requests)?)?
+            } else {
+                writeln!(out, "{}", serde_json::to_string(&pull_requests)?)?
+            }
+        }
+        (OutputFormat::Markdown, _) => {
+            for group in groups {
+                if groups.len() > 1 {
+                    writeln!(out, "# {}", group.project)?;
+                    writeln!(out)?;
+                }
+                for pr in &group.pull_requests {
+                    print_markdown(out, pr, &group.client)?;
+                }
+            }
+        }
+        (OutputFormat::Ndjson, _) => {
+            for group in groups {
+                for pr in &group.pull_requests {
+                    serde_json::to_writer(&mut *out, &JsonPullRequest::new(pr, &group.client))?;
+                    writeln!(out)?;
+                    out.flush()?;
+                }
+            }
+        }
+    }
+    if total == 0 && !options.quiet {
+        eprintln!("No {} pull requests.", options.status.description());
+    }
+    Ok(())
+}
+
+/// Prints a section with the title, author and description of `pr`.
+fn print_markdown(
+    out: &mut dyn Write,
+    pr: &PullRequest,
+    client: &AzureClient,
+) -> std::io::Result<()> {
+    let title = text::sanitize(pr.title.trim_end()).replace('\n', " ");
+    writeln!(out, "## {title} ({})", pr.pull_request_id)?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "By {}, {}",
+        text::sanitize(&pr.created_by.display_name),
+        client.web_url(pr)
+    )?;
+    writeln!(out)?;
+    // Front matter in the middle of a document would be a horizontal rule
+    // or a heading, so it is shown like in the text output instead.
+    let (metadata, elements): (Vec<_>, Vec<_>) = description_elements(pr)
+        .into_iter()
+        .partition(|element| matches!(element, text::TextElement::Metadata(_)));
+    for element in metadata {
+        if let text::TextElement::Metadata(metadata) = element {
+            writeln!(out, "_{}_", metadata_summary(&metadata))?;
+            writeln!(out)?;
+        }
+    }
+    if !elements.is_empty() {
+        writeln!(out, "{}", text::to_markdown(&elements))?;
+    }
+    Ok(())
+}
+
+/// Opens the pull requests in the browser, or the pull request overview of
+/// the projects if there are too many of them.
+fn open_in_browser(groups: &[ProjectPullRequests]) -> Result<()> {
+    let total = groups.iter().map(|g| g.pull_requests.len()).sum::<usize>();
+    let urls: Vec<String> = if total <= MAX_OPENED_PULL_REQUESTS {
+        groups
+            .iter()
+            .flat_map(|g| g.pull_requests.iter().map(|pr| g.client.web_url(pr)))
+            .collect()
+    } else {
+        groups
+            .iter()
+            .map(|g| g.client.pull_requests_web_url())
+            .collect()
+    };
+    for url in urls {
+        open::that(&url).with_context(|| format!("Failed to open {url} in a browser"))?;
+    }
+    Ok(())
+}
+
+/// Where and as whom to fetch the pull requests.
+struct Connection {
+    http: reqwest::Client,
     organization: String,
-    /// Name of the team project in Azure DevOps
+    projects: Vec<String>,
+    username: String,
+    credentials: azure::Credentials,
+}
+
+impl Connection {
+    fn client(&self, project: &str, options: &Options) -> Result<AzureClient> {
+        let client = AzureClient::new(
+            self.http.clone(),
+            &self.organization,
+            project,
+            self.credentials.clone(),
+            &options.api_version,
+        )
+        .with_api_path(&options.api_path)
+        .with_base_url(&options.base_url)?;
+        Ok(client)
+    }
+}
+
+struct ProjectPullRequests {
     project: String,
+    client: AzureClient,
+    summary: Summary,
+    pull_requests: Vec<PullRequest>,
 }
 
-fn main() -> Result<()> {
-    let options = Options::parse();
-
-    let pat = std::fs::read_to_string(&options.pat_file)
-        .with_context(|| format!("Failed to read PAT from {}", options.pat_file.display()))?;
-    let client = reqwest::blocking::Client::new();
-    let dev_api = Early::new("https", "dev.azure.com")
-        .path(&options.organization)
-        .path(&options.project)
-        .path("_apis")
-        .query("api_version", "7.0");
-
-    let pull_requests = dev_api.path("git").path("pullrequests").build();
-
-    let pull_requests: Reply<PullRequest> = client
-        .get(pull_requests)
-        .basic_auth(&options.username, Some(pat))
-        .send()?
-        .error_for_status()
-        .context("Failed to get PR list")?
-        .json()
-        .context("Failed to parse PR list")?;
-
-    for pr in pull_requests.value.into_iter().filter(|pr| !pr.is_draft) {
-        println!(
-            "{}: {} ({})",
-            pr.created_by.display_name,
-            pr.title.trim_end(),
-            pr.pull_request_id
-        );
-        if let Some(description) = pr.description {
-            if description != pr.title {
-                println!();
-                for element in text::parse(&description) {
-                    match element {
-                        text::TextElement::Paragraph(p) => {
-                            for line in textwrap::wrap(&p, 70) {
-                                println!("   {line}");
-                            }
-                            println!();
-                        }
-                        text::TextElement::ListEntry(t) => {
-                            println!("   - {t}");
-                        }
+/// Counts over all fetched pull requests, before any filtering.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Summary {
+    total: usize,
+    drafts: usize,
+    awaiting_review: usize,
+}
+
+impl Summary {
+    fn new(pull_requests: &[PullRequest], username: &str) -> Self {
+        Summary {
+            total: pull_requests.len(),
+            drafts: pull_requests.iter().filter(|pr| pr.is_draft).count(),
+            awaiting_review: pull_requests
+                .iter()
+                .filter(|pr| pr.is_awaiting_review_by(username))
+                .count(),
+        }
+    }
+
+    fn add(&mut self, other: &Summary) {
+        self.total += other.total;
+        self.drafts += other.drafts;
+        self.awaiting_review += other.awaiting_review;
+    }
+
+    fn line(&self, status: StatusFilter) -> String {
+        format!(
+            "{} {}, {} draft, {} awaiting your review",
+            self.total,
+            status.description(),
+            self.drafts,
+            self.awaiting_review
+        )
+    }
+}
+
+/// Applies the filters and sort order given on the command line.
+fn select(pull_requests: Vec<PullRequest>, options: &Options, username: &str) -> Vec<PullRequest> {
+    let cutoff = options
+        .since
+        .and_then(|since| Utc::now().checked_sub_signed(since));
+    let mut pull_requests: Vec<PullRequest> = pull_requests
+        .into_iter()
+        // Azure DevOps already filters by these, but saved pull requests don't
+        .filter(|pr| options.status.matches(&pr.status))
+        .filter(|pr| match &options.target {
+            Some(target) => pr.merges_into(target),
+            None => true,
+        })
+        .filter(|pr| match &options.repository {
+            Some(repository) => pr.repository.name.eq_ignore_ascii_case(repository),
+            None => true,
+        })
+        .filter(|pr| options.include_drafts || !pr.is_draft)
+        .filter(|pr| !options.conflicts_only || pr.has_conflicts())
+        .filter(|pr| options.authors.is_empty() || pr.created_by.matches_any(&options.authors))
+        .filter(|pr| !pr.created_by.matches_any(&options.excluded_authors))
+        .filter(|pr| pr.has_labels(&options.labels))
+        .filter(|pr| !options.me || pr.created_by.is(username))
+        .filter(|pr| !options.awaiting_my_review || pr.is_awaiting_review_by(username))
+        .filter(|pr| !options.ready || pr.approvals().is_ready())
+        .filter(|pr| match cutoff {
+            Some(cutoff) => pr.closed_date.unwrap_or(pr.creation_date) >= cutoff,
+            None => true,
+        })
+        .filter(|pr| match options.created_after {
+            Some(after) => pr.creation_date >= after,
+            None => true,
+        })
+        .filter(|pr| match options.created_before {
+            Some(before) => pr.creation_date < before,
+            None => true,
+        })
+        .collect();
+
+    match options.sort {
+        SortKey::Id => pull_requests.sort_by_key(|pr| pr.pull_request_id),
+        SortKey::Author => pull_requests.sort_by(|a, b| {
+            a.created_by
+                .display_name
+                .to_lowercase()
+                .cmp(&b.created_by.display_name.to_lowercase())
+        }),
+        SortKey::Title => pull_requests.sort_by(|a, b| a.title.cmp(&b.title)),
+        SortKey::Date => pull_requests.sort_by_key(|pr| std::cmp::Reverse(pr.creation_date)),
+    }
+    if options.reverse {
+        pull_requests.reverse();
+    }
+    pull_requests
+}
+
+/// Whether `pr` has unresolved threads if --has-unresolved is given, or none
+/// if --all-resolved is given.
+fn matches_thread_filter(pr: &PullRequest, options: &Options) -> bool {
+    let unresolved = pr.unresolved_thread_count() > 0;
+    (!options.has_unresolved || unresolved) && (!options.all_resolved || !unresolved)
+}
+
+fn read_pat(pat_file: &Path) -> Result<String> {
+    let pat = std::fs::read_to_string(pat_file)
+        .with_context(|| format!("Failed to read PAT from {}", pat_file.display()))?;
+    Ok(pat.trim().to_string())
+}
+
+fn required<T>(argument: Option<T>, configured: Option<T>, name: &str) -> Result<T> {
+    argument.or(configured).with_context(|| {
+        format!(
+            "No {name} given on the command line or in {}",
+            config::FILE_NAME
+        )
+    })
+}
+
+fn group_pull_requests(
+    pull_requests: &[PullRequest],
+    group_by: GroupBy,
+) -> BTreeMap<String, Vec<&PullRequest>> {
+    let mut groups: BTreeMap<String, Vec<&PullRequest>> = BTreeMap::new();
+    for pr in pull_requests {
+        groups.entry(group_name(pr, group_by)).or_default().push(pr);
+    }
+    groups
+}
+
+fn group_name(pr: &PullRequest, group_by: GroupBy) -> String {
+    match group_by {
+        GroupBy::Branch => pr.target_branch().to_string(),
+        GroupBy::Author => text::sanitize(&pr.created_by.display_name),
+    }
+}
+
+/// Prints a line like "Jane Doe: 3" per author or target branch, in all the
+/// projects together, with the most pull requests first.
+fn print_counts(
+    out: &mut dyn Write,
+    groups: &[ProjectPullRequests],
+    options: &Options,
+) -> std::io::Result<()> {
+    let Some(count_by) = options.count_by else {
+        return Ok(());
+    };
+    for (name, count) in counts(groups.iter().flat_map(|g| &g.pull_requests), count_by) {
+        writeln!(out, "{name}: {count}")?;
+    }
+    Ok(())
+}
+
+fn counts<'a>(
+    pull_requests: impl IntoIterator<Item = &'a PullRequest>,
+    count_by: GroupBy,
+) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for pr in pull_requests {
+        *counts.entry(group_name(pr, count_by)).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+}
+
+/// How the lines under each title are laid out.
+struct Layout {
+    /// Width to wrap descriptions at.
+    width: usize,
+    /// What to start the lines under each title with.
+    indent: String,
+}
+
+fn print_text<'a>(
+    out: &mut dyn Write,
+    pull_requests: impl IntoIterator<Item = &'a PullRequest>,
+    styles: &Styles,
+    layout: &Layout,
+    client: &AzureClient,
+    options: &Options,
+) -> std::io::Result<()> {
+    let fields = &options.fields;
+    for pr in pull_requests {
+        let url = client.web_url(pr);
+        let indent = &layout.indent;
+        if fields.is_empty() {
+            writeln!(
+                out,
+                "{}: {} ({})",
+                text::sanitize(&pr.created_by.display_name).style(styles.author),
+                title(pr, &url, styles),
+                pr.pull_request_id.style(styles.id)
+            )?;
+            writeln!(out, "{indent}{url}")?;
+            writeln!(
+                out,
+                "{indent}{}: {}",
+                text::sanitize(&pr.repository.name),
+                branches(pr)
+            )?;
+            match pr.closed_date {
+                Some(closed) => writeln!(
+                    out,
+                    "{indent}Created {}, {} {}",
+                    pr.creation_date.format("%Y-%m-%d %H:%M"),
+                    pr.status,
+                    closed.format("%Y-%m-%d %H:%M")
+                )?,
+                None => writeln!(
+                    out,
+                    "{indent}Created {}, {}",
+                    pr.creation_date.format("%Y-%m-%d %H:%M"),
+                    pr.status
+                )?,
+            }
+            print_labels(out, pr, indent)?;
+            if options.threads || pr.comment_count() > 0 {
+                writeln!(out, "{indent}{}", comments(pr))?;
+            }
+            if let Some(changes) = changes(pr) {
+                writeln!(out, "{indent}Changes: {changes}")?;
+            }
+            print_reviewers(out, pr, indent)?;
+            print_work_items(out, pr, indent)?;
+            if !options.no_description {
+                print_description(out, pr, styles, layout)?;
+            }
+        } else {
+            let line: Vec<String> = fields
+                .iter()
+                .filter_map(|field| match field {
+                    Field::Id => Some(pr.pull_request_id.style(styles.id).to_string()),
+                    Field::Author => Some(
+                        text::sanitize(&pr.created_by.display_name)
+                            .style(styles.author)
+                            .to_string(),
+                    ),
+                    Field::Title => Some(title(pr, &url, styles)),
+                    Field::Url => Some(url.clone()),
+                    Field::Repository => Some(text::sanitize(&pr.repository.name)),
+                    Field::Branch => Some(branches(pr)),
+                    Field::Date => Some(pr.creation_date.format("%Y-%m-%d %H:%M").to_string()),
+                    Field::Status => Some(pr.status.clone()),
+                    Field::Comments => Some(comments(pr)),
+                    Field::Changes => changes(pr),
+                    Field::Approvals => Some(pr.approvals().to_string()),
+                    Field::Labels | Field::Reviewers | Field::WorkItems | Field::Description => {
+                        None
+                    }
+                })
+                .collect();
+            if !line.is_empty() {
+                writeln!(out, "{}", line.join("  "))?;
+            }
+            for field in fields {
+                match field {
+                    Field::Labels => print_labels(out, pr, indent)?,
+                    Field::Reviewers => print_reviewers(out, pr, indent)?,
+                    Field::WorkItems => print_work_items(out, pr, indent)?,
+                    Field::Description if !options.no_description => {
+                        print_description(out, pr, styles, layout)?
                     }
+                    _ => {}
                 }
             }
         }
-        println!();
+        writeln!(out)?;
     }
     Ok(())
 }
+
+fn title(pr: &PullRequest, url: &str, styles: &Styles) -> String {
+    let title = text::sanitize(pr.title.trim_end()).replace('\n', " ");
+    let title = if styles.hyperlinks {
+        hyperlink(&title, url)
+    } else {
+        title
+    };
+    let title = if pr.has_conflicts() {
+        format!("{} {title}", "[CONFLICTS]".style(styles.conflicts))
+    } else {
+        title
+    };
+    if pr.is_draft {
+        format!("{} {title}", "[DRAFT]".style(styles.draft))
+    } else {
+        title
+    }
+}
+
+const HYPERLINK_START: &str = "\x1b]8;;";
+const HYPERLINK_END: &str = "\x1b\\";
+
+/// `text` linking to `url` in terminals supporting OSC 8 hyperlinks.
+fn hyperlink(text: &str, url: &str) -> String {
+    format!("{HYPERLINK_START}{url}{HYPERLINK_END}{text}{HYPERLINK_START}{HYPERLINK_END}")
+}
+
+/// The number of columns `text` takes on screen, without the escapes for
+/// colors and hyperlinks.
+fn visible_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut rest = text;
+    while let Some(start) = rest.find(HYPERLINK_START) {
+        let after = &rest[start + HYPERLINK_START.len()..];
+        let Some(end) = after.find(HYPERLINK_END) else {
+            break;
+        };
+        width += textwrap::core::display_width(&rest[..start]);
+        rest = &after[end + HYPERLINK_END.len()..];
+    }
+    width + textwrap::core::display_width(rest)
+}
+
+fn branches(pr: &PullRequest) -> String {
+    match pr.short_source_commit() {
+        Some(commit) => format!("{} → {} ({commit})", pr.source_branch(), pr.target_branch()),
+        None => format!("{} → {}", pr.source_branch(), pr.target_branch()),
+    }
+}
+
+fn comments(pr: &PullRequest) -> String {
+    format!(
+        "{} comments, {} unresolved",
+        pr.comment_count(),
+        pr.unresolved_thread_count()
+    )
+}
+
+/// The number of changed files, like "8 files (5 edited, 2 added, 1 deleted)",
+/// if they were fetched.
+fn changes(pr: &PullRequest) -> Option<String> {
+    let counts = pr.change_counts.as_ref()?;
+    let total: usize = counts.values().sum();
+    let files = if total == 1 { "file" } else { "files" };
+    if counts.len() < 2 {
+        return Some(format!("{total} {files}"));
+    }
+    let kinds: Vec<String> = counts
+        .iter()
+        .map(|(kind, count)| {
+            let kind = match kind.to_lowercase().as_str() {
+                "add" => "added".to_string(),
+                "edit" => "edited".to_string(),
+                "delete" => "deleted".to_string(),
+                "rename" => "renamed".to_string(),
+                kind => kind.to_string(),
+            };
+            format!("{count} {kind}")
+        })
+        .collect();
+    Some(format!("{total} {files} ({})", kinds.join(", ")))
+}
+
+fn print_labels(out: &mut dyn Write, pr: &PullRequest, indent: &str) -> std::io::Result<()> {
+    if !pr.labels.is_empty() {
+        let labels: Vec<String> = pr
+            .labels
+            .iter()
+            .map(|label| text::sanitize(&label.name))
+            .collect();
+        writeln!(out, "{indent}Labels: {}", labels.join(", "))?;
+    }
+    Ok(())
+}
+
+fn print_reviewers(out: &mut dyn Write, pr: &PullRequest, indent: &str) -> std::io::Result<()> {
+    if !pr.reviewers.is_empty() {
+        let reviewers: Vec<String> = pr
+            .reviewers
+            .iter()
+            .map(|reviewer| {
+                format!(
+                    "{} ({})",
+                    text::sanitize(&reviewer.person.display_name),
+                    reviewer.vote_description()
+                )
+            })
+            .collect();
+        writeln!(out, "{indent}Reviewers: {}", reviewers.join(", "))?;
+        writeln!(out, "{indent}Approvals: {}", pr.approvals())?;
+    }
+    Ok(())
+}
+
+fn print_work_items(out: &mut dyn Write, pr: &PullRequest, indent: &str) -> std::io::Result<()> {
+    if !pr.work_item_refs.is_empty() {
+        let work_items: Vec<String> = pr
+            .work_item_refs
+            .iter()
+            .map(|work_item| format!("#{}", work_item.id))
+            .collect();
+        writeln!(out, "{indent}Linked work items: {}", work_items.join(", "))?;
+    }
+    Ok(())
+}
+
+fn print_description(
+    out: &mut dyn Write,
+    pr: &PullRequest,
+    styles: &Styles,
+    layout: &Layout,
+) -> std::io::Result<()> {
+    let Layout { width, indent } = layout;
+    let width = *width;
+    let elements = description_elements(pr);
+    if elements.is_empty() {
+        return Ok(());
+    }
+    let mut previous_was_entry = false;
+    for element in elements {
+        // Blocks are separated by blank lines, the entries of a list aren't
+        let is_entry = matches!(
+            element,
+            text::TextElement::ListEntry(..) | text::TextElement::OrderedEntry(..)
+        );
+        if !(is_entry && previous_was_entry) {
+            writeln!(out)?;
+        }
+        previous_was_entry = is_entry;
+        match element {
+            text::TextElement::Paragraph(p) => {
+                print_wrapped(out, &render_inline(&p, styles), indent, width)?;
+            }
+            text::TextElement::ListEntry(depth, t) => {
+                let bullet = format!("{indent}{:depth$}- ", "");
+                print_wrapped(out, &render_inline(&t, styles), &bullet, width)?;
+            }
+            text::TextElement::OrderedEntry(n, t) => {
+                let number = format!("{indent}{n}. ");
+                print_wrapped(out, &render_inline(&t, styles), &number, width)?;
+            }
+            text::TextElement::CodeBlock(code) => {
+                for line in code.lines() {
+                    writeln!(out, "{indent}    {line}")?;
+                }
+            }
+            text::TextElement::Quote(q) => {
+                let bar = format!("{indent}│ ");
+                for line in wrap(&render_inline(&q, styles), &bar, &bar, width) {
+                    writeln!(out, "{line}")?;
+                }
+            }
+            text::TextElement::Table(rows) => {
+                print_table(out, &rows, styles, indent)?;
+            }
+            text::TextElement::Metadata(metadata) => {
+                let summary = metadata_summary(&metadata);
+                print_wrapped(
+                    out,
+                    &summary.style(styles.italic).to_string(),
+                    indent,
+                    width,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints a table with the columns as wide as their widest cell.
+fn print_table(
+    out: &mut dyn Write,
+    rows: &[Vec<String>],
+    styles: &Styles,
+    indent: &str,
+) -> std::io::Result<()> {
+    let rows: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| render_inline(&cell.replace("\\|", "|"), styles))
+                .collect()
+        })
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| visible_width(cell))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for (index, row) in rows.iter().enumerate() {
+        let mut line = format!("{indent}|");
+        for (column, width) in widths.iter().enumerate() {
+            let cell = row.get(column).map_or("", String::as_str);
+            let padding = width - visible_width(cell);
+            line.push_str(&format!(" {cell}{} |", " ".repeat(padding)));
+        }
+        writeln!(out, "{line}")?;
+        if index == 0 {
+            let separators: Vec<String> =
+                widths.iter().map(|width| "-".repeat(width + 2)).collect();
+            writeln!(out, "{indent}|{}|", separators.join("|"))?;
+        }
+    }
+    Ok(())
+}
+
+/// The lines of front matter on a single line, like "risk: low · area: billing".
+fn metadata_summary(metadata: &str) -> String {
+    metadata
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// The parsed description of `pr`, or nothing if it has no description, the
+/// description only repeats the title or it is blank.
+fn description_elements(pr: &PullRequest) -> Vec<text::TextElement> {
+    match &pr.description {
+        Some(description) if *description != pr.title => {
+            // Parsed before sanitizing, which drops the carriage returns
+            // that may be all that separates the lines.
+            let mut elements: Vec<text::TextElement> = text::parse(description)
+                .into_iter()
+                .map(|element| element.map(|text| text::sanitize(&text)))
+                .collect();
+            elements.retain(|element| !element.is_empty());
+            elements
+        }
+        _ => vec![],
+    }
+}
+
+/// Prints `text` wrapped to `width` columns, starting with `first_indent`
+/// and aligning continuation lines with the text after it.
+fn print_wrapped(
+    out: &mut dyn Write,
+    text: &str,
+    first_indent: &str,
+    width: usize,
+) -> std::io::Result<()> {
+    let hanging_indent = " ".repeat(textwrap::core::display_width(first_indent));
+    for line in wrap(text, first_indent, &hanging_indent, width) {
+        writeln!(out, "{line}")?;
+    }
+    Ok(())
+}
+
+/// Wraps `text` like [textwrap::wrap], but without counting the URLs of
+/// hyperlinks toward the width. Hyperlinks split over lines are closed at
+/// the end of a line and opened again after the indent of the next one.
+fn wrap(text: &str, initial_indent: &str, subsequent_indent: &str, width: usize) -> Vec<String> {
+    // textwrap skips CSI sequences when measuring, so while wrapping the
+    // hyperlinks are replaced by CSI sequences holding the index of their
+    // URL, where an empty URL ends the hyperlink.
+    let mut urls: Vec<&str> = vec![];
+    let mut masked = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(HYPERLINK_START) {
+        let after = &rest[start + HYPERLINK_START.len()..];
+        let Some(end) = after.find(HYPERLINK_END) else {
+            break;
+        };
+        masked.push_str(&rest[..start]);
+        masked.push_str(&format!("\x1b[{}y", urls.len()));
+        urls.push(&after[..end]);
+        rest = &after[end + HYPERLINK_END.len()..];
+    }
+    masked.push_str(rest);
+    let options = textwrap::Options::new(width)
+        .initial_indent(initial_indent)
+        .subsequent_indent(subsequent_indent);
+
+    let mut open: Option<&str> = None;
+    let mut lines = vec![];
+    for (i, line) in textwrap::wrap(&masked, options).iter().enumerate() {
+        let indent = if i == 0 {
+            initial_indent
+        } else {
+            subsequent_indent
+        };
+        let (indent, mut rest) = match line.strip_prefix(indent) {
+            Some(rest) => (indent, rest),
+            None => ("", line.as_ref()),
+        };
+        let mut result = indent.to_string();
+        if let Some(url) = open {
+            result.push_str(&format!("{HYPERLINK_START}{url}{HYPERLINK_END}"));
+        }
+        while let Some(start) = rest.find("\x1b[") {
+            let after = &rest[start + 2..];
+            let digits = after
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(after.len());
+            let url = match after[..digits].parse::<usize>() {
+                Ok(index) if after[digits..].starts_with('y') => urls.get(index).copied(),
+                _ => None,
+            };
+            match url {
+                Some(url) => {
+                    result.push_str(&rest[..start]);
+                    result.push_str(&format!("{HYPERLINK_START}{url}{HYPERLINK_END}"));
+                    open = Some(url).filter(|url| !url.is_empty());
+                    rest = &after[digits + 1..];
+                }
+                None => {
+                    result.push_str(&rest[..start + 2]);
+                    rest = after;
+                }
+            }
+        }
+        result.push_str(rest);
+        if open.is_some() {
+            result.push_str(&format!("{HYPERLINK_START}{HYPERLINK_END}"));
+        }
+        lines.push(result);
+    }
+    lines
+}
+
+fn render_inline(text: &str, styles: &Styles) -> String {
+    let emojify = |t: String| if styles.emoji { text::emojify(&t) } else { t };
+    text::spans(text)
+        .into_iter()
+        .map(|span| match span {
+            text::Span::Plain(t) => emojify(t),
+            text::Span::Bold(t) => emojify(t).style(styles.bold).to_string(),
+            text::Span::Italic(t) => emojify(t).style(styles.italic).to_string(),
+            text::Span::Link(t, url) if styles.hyperlinks => hyperlink(&emojify(t), &url),
+            text::Span::Link(t, url) => format!("{} ({url})", emojify(t)),
+            text::Span::Code(t) => match styles.code {
+                Some(style) => t.style(style).to_string(),
+                None => format!("`{t}`"),
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pat_is_read_without_surrounding_whitespace() {
+        let pat_file = std::env::temp_dir().join(format!("prlist-pat-{}", std::process::id()));
+        std::fs::write(&pat_file, "  secret-token \n").unwrap();
+        let pat = read_pat(&pat_file);
+        std::fs::remove_file(&pat_file).unwrap();
+        assert_eq!(pat.unwrap(), "secret-token");
+    }
+
+    /// A pull request to test with, also used by the tests of the other modules.
+    pub(crate) fn pull_request(title: &str, description: &str) -> PullRequest {
+        let mut pr: PullRequest = serde_json::from_str(
+            r#"{
+                "title": "",
+                "isDraft": false,
+                "pullRequestId": 1,
+                "createdBy": { "displayName": "Mallory" },
+                "sourceRefName": "refs/heads/evil",
+                "targetRefName": "refs/heads/main",
+                "creationDate": "2022-11-20T10:15:00Z",
+                "status": "active",
+                "repository": { "id": "1", "name": "repo" }
+            }"#,
+        )
+        .unwrap();
+        pr.title = title.to_string();
+        pr.description = Some(description.to_string());
+        pr
+    }
+
+    #[test]
+    fn positional_arguments_start_with_the_username_without_pat_file() {
+        let parse = |args: &[&str]| Options::try_parse_from(args).unwrap().without_pat_file();
+        let options = parse(&["prlist", "--pat", "secret", "jane", "contoso", "webshop"]);
+        assert_eq!(options.pat_file, None);
+        assert_eq!(options.username.as_deref(), Some("jane"));
+        assert_eq!(options.organization.as_deref(), Some("contoso"));
+        assert_eq!(options.project.as_deref(), Some("webshop"));
+        let options = parse(&[
+            "prlist", "--pat", "secret", "-", "jane", "contoso", "webshop",
+        ]);
+        assert_eq!(options.username.as_deref(), Some("jane"));
+        assert_eq!(options.project.as_deref(), Some("webshop"));
+        let options = parse(&["prlist", "--auth", "az-cli", "jane", "contoso", "webshop"]);
+        assert_eq!(options.pat_file, None);
+        assert_eq!(options.username.as_deref(), Some("jane"));
+        assert_eq!(options.project.as_deref(), Some("webshop"));
+        let options = parse(&[
+            "prlist",
+            "--from-file",
+            "f.json",
+            "jane",
+            "contoso",
+            "webshop",
+        ]);
+        assert_eq!(options.pat_file, None);
+        assert_eq!(options.project.as_deref(), Some("webshop"));
+        let options = parse(&["prlist", "pat", "jane", "contoso"]);
+        assert_eq!(options.pat_file, Some(PathBuf::from("pat")));
+        assert_eq!(options.project, None);
+    }
+
+    #[test]
+    fn pull_requests_are_filtered_by_unresolved_threads() {
+        let mut blocked = pull_request("Add widgets", "");
+        blocked.threads = serde_json::from_str(
+            r#"[
+                { "status": "active", "comments": [{ "commentType": "text" }] },
+                { "status": "fixed", "comments": [{ "commentType": "text" }] }
+            ]"#,
+        )
+        .unwrap();
+        let mut addressed = pull_request("Fix widgets", "");
+        addressed.threads = serde_json::from_str(
+            r#"[{ "status": "closed", "comments": [{ "commentType": "text" }] }]"#,
+        )
+        .unwrap();
+        let untouched = pull_request("Remove gadgets", "");
+
+        let options = Options::try_parse_from(["prlist", "--has-unresolved"]).unwrap();
+        assert!(matches_thread_filter(&blocked, &options));
+        assert!(!matches_thread_filter(&addressed, &options));
+        assert!(!matches_thread_filter(&untouched, &options));
+        let options = Options::try_parse_from(["prlist", "--all-resolved"]).unwrap();
+        assert!(!matches_thread_filter(&blocked, &options));
+        assert!(matches_thread_filter(&addressed, &options));
+        assert!(matches_thread_filter(&untouched, &options));
+        assert!(Options::try_parse_from(["prlist", "--has-unresolved", "--all-resolved"]).is_err());
+    }
+
+    #[test]
+    fn saved_pull_requests_are_filtered_like_the_server_does() {
+        let pull_requests = || {
+            let mut pull_requests = vec![
+                pull_request("Add widgets", ""),
+                pull_request("Fix widgets", ""),
+                pull_request("Remove gadgets", ""),
+                pull_request("Rename gadgets", ""),
+            ];
+            pull_requests[1].target_ref_name = "refs/heads/dev".into();
+            pull_requests[2].status = "completed".into();
+            pull_requests[3].repository.name = "Shop".into();
+            pull_requests
+        };
+        let titles = |args: &[&str]| -> Vec<String> {
+            let options = Options::try_parse_from(args).unwrap();
+            select(pull_requests(), &options, "")
+                .into_iter()
+                .map(|pr| pr.title)
+                .collect()
+        };
+        assert_eq!(titles(&["prlist", "--target", "dev"]), ["Fix widgets"]);
+        assert_eq!(
+            titles(&[
+                "prlist",
+                "--target",
+                "refs/heads/main",
+                "--repository",
+                "shop"
+            ]),
+            ["Rename gadgets"]
+        );
+        assert_eq!(
+            titles(&["prlist", "--status", "completed"]),
+            ["Remove gadgets"]
+        );
+        assert_eq!(titles(&["prlist", "--status", "all"]).len(), 4);
+    }
+
+    #[test]
+    fn date_sorting_puts_the_newest_first() {
+        let pull_requests = || {
+            [
+                "2022-11-20T10:15:00Z",
+                "2023-01-02T08:00:00Z",
+                "2022-12-24T18:30:00Z",
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(i, date)| {
+                let mut pr = pull_request("Add widgets", "");
+                pr.pull_request_id = i as u32 + 1;
+                pr.creation_date = date.parse().unwrap();
+                pr
+            })
+            .collect::<Vec<_>>()
+        };
+        let ids = |args: &[&str]| -> Vec<u32> {
+            let options = Options::try_parse_from(args).unwrap();
+            select(pull_requests(), &options, "")
+                .iter()
+                .map(|pr| pr.pull_request_id)
+                .collect()
+        };
+        assert_eq!(ids(&["prlist", "--sort", "date"]), [2, 3, 1]);
+        assert_eq!(ids(&["prlist", "--sort", "date", "--reverse"]), [1, 3, 2]);
+    }
+
+    #[test]
+    fn control_characters_in_titles_are_escaped() {
+        let pr = pull_request("Harmless\r\u{1b}[31mEvil", "");
+        let title = title(&pr, "https://example.com", &Styles::new(false));
+        assert!(!title.contains('\r'));
+        assert!(!title.contains('\x1b'));
+        assert_eq!(title, "Harmless\\u{1b}[31mEvil");
+    }
+
+    #[test]
+    fn lines_separated_by_carriage_returns_are_kept_apart() {
+        let pr = pull_request(
+            "Add widgets",
+            "First line\rsecond line\r\rNew paragraph\r- item",
+        );
+        assert_eq!(
+            description_elements(&pr),
+            vec![
+                text::TextElement::Paragraph("First line second line".into()),
+                text::TextElement::Paragraph("New paragraph".into()),
+                text::TextElement::ListEntry(0, "item".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_description_is_not_printed() {
+        let pr = pull_request("Fix the build", "   \n\n  ");
+        assert_eq!(description_elements(&pr), vec![]);
+    }
+
+    #[test]
+    fn wide_characters_are_wrapped_by_display_width() {
+        let pr = pull_request(
+            "Translate the shop",
+            "这个拉取请求把商店的所有页面翻译成中文，包括结账页面和帮助页面。\n\n\
+             - 翻译商品目录、购物车和订单历史记录页面 🎉🎉🎉",
+        );
+        let width = 30;
+        let mut out = vec![];
+        print_description(&mut out, &pr, &Styles::new(false), &layout(width, 3)).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.lines().count() > 4);
+        for line in out.lines() {
+            assert!(
+                textwrap::core::display_width(line) <= width,
+                "{line:?} is wider than {width} columns"
+            );
+        }
+    }
+
+    fn layout(width: usize, indent: usize) -> Layout {
+        Layout {
+            width,
+            indent: " ".repeat(indent),
+        }
+    }
+
+    #[test]
+    fn descriptions_are_indented_with_hanging_list_entries() {
+        let pr = pull_request(
+            "Add widgets",
+            "Adds widgets.\n\n- a list entry long enough to wrap\n  - nested",
+        );
+        let mut out = vec![];
+        print_description(&mut out, &pr, &Styles::new(false), &layout(24, 1)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "
+ Adds widgets.
+
+ - a list entry long
+   enough to wrap
+   - nested
+"
+        );
+    }
+
+    #[test]
+    fn hyperlinks_are_wrapped_by_their_text() {
+        let pr = pull_request(
+            "Add widgets",
+            "See [the design](https://example.com/a/very/long/path) before merging.",
+        );
+        let mut out = vec![];
+        print_description(&mut out, &pr, &Styles::new(true), &layout(14, 3)).unwrap();
+        let open = "\x1b]8;;https://example.com/a/very/long/path\x1b\\";
+        let close = "\x1b]8;;\x1b\\";
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("\n   See {open}the{close}\n   {open}design{close}\n   before\n   merging.\n")
+        );
+    }
+
+    #[test]
+    fn pull_requests_are_grouped_by_author() {
+        let mut pull_requests = vec![
+            pull_request("Add widgets", ""),
+            pull_request("Fix widgets", ""),
+            pull_request("Remove gadgets", ""),
+        ];
+        pull_requests[2].created_by.display_name = "Al".into();
+        let groups = group_pull_requests(&pull_requests, GroupBy::Author);
+        let sizes: Vec<(&str, usize)> = groups
+            .iter()
+            .map(|(name, members)| (name.as_str(), members.len()))
+            .collect();
+        assert_eq!(sizes, [("Al", 1), ("Mallory", 2)]);
+    }
+
+    #[test]
+    fn list_only_descriptions_have_no_stray_blank_lines() {
+        let pr = pull_request("Add widgets", "- one\n- two\n\n\n");
+        let mut out = vec![];
+        print_description(&mut out, &pr, &Styles::new(false), &layout(70, 3)).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\n   - one\n   - two\n");
+    }
+
+    #[test]
+    fn description_blocks_are_separated_by_one_blank_line() {
+        let pr = pull_request("Add widgets", "Adds:\n- one\n- two\nAnd more.\n\n> Quoted");
+        let mut out = vec![];
+        print_description(&mut out, &pr, &Styles::new(false), &layout(70, 3)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "
+   Adds:
+
+   - one
+   - two
+
+   And more.
+
+   │ Quoted
+"
+        );
+    }
+
+    #[test]
+    fn tables_are_aligned() {
+        let pr = pull_request(
+            "Speed up the build",
+            "| Step | Time |\n|---|---|\n| build | 3 s |\n| integration tests | 12 s |",
+        );
+        let mut out = vec![];
+        print_description(&mut out, &pr, &Styles::new(false), &layout(70, 3)).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "
+   | Step              | Time |
+   |-------------------|------|
+   | build             | 3 s  |
+   | integration tests | 12 s |
+"
+        );
+    }
+
+    #[test]
+    fn tables_are_aligned_by_visible_text() {
+        let pr = pull_request(
+            "Document widgets",
+            "| Page | Note |\n|---|---|\n| [docs](https://example.com/a/very/long/path) | x \\| y |",
+        );
+        let mut out = vec![];
+        print_description(&mut out, &pr, &Styles::new(true), &layout(70, 3)).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().skip(1).collect();
+        assert_eq!(lines.len(), 3);
+        for line in &lines {
+            assert_eq!(visible_width(line), visible_width(lines[1]), "{line:?}");
+        }
+        assert!(lines[2].ends_with("| x | y |"), "{:?}", lines[2]);
+    }
+
+    #[test]
+    fn counts_are_sorted_by_count_then_name() {
+        let mut pull_requests = vec![
+            pull_request("Add widgets", ""),
+            pull_request("Remove gadgets", ""),
+            pull_request("Fix widgets", ""),
+            pull_request("Fix gadgets", ""),
+        ];
+        pull_requests[1].created_by.display_name = "Bob".into();
+        pull_requests[3].created_by.display_name = "Al".into();
+        assert_eq!(
+            counts(&pull_requests, GroupBy::Author),
+            [("Mallory".into(), 2), ("Al".into(), 1), ("Bob".into(), 1)]
+        );
+    }
+
+    #[test]
+    fn changes_are_counted_by_kind() {
+        let mut pr = pull_request("Add widgets", "");
+        assert_eq!(changes(&pr), None);
+        pr.change_counts = Some(BTreeMap::from([("Edit".into(), 5), ("Add".into(), 2)]));
+        assert_eq!(changes(&pr).unwrap(), "7 files (2 added, 5 edited)");
+        pr.change_counts = Some(BTreeMap::from([("Edit".into(), 1)]));
+        assert_eq!(changes(&pr).unwrap(), "1 file");
+    }
+
+    #[test]
+    fn summary_counts_drafts_and_pending_reviews() {
+        let mut draft = pull_request("Draft", "");
+        draft.is_draft = true;
+        let mut reviewed = pull_request("Reviewed", "");
+        reviewed.reviewers = serde_json::from_str(
+            r#"[{ "displayName": "Jane Doe", "uniqueName": "jane@contoso.com", "vote": 0 }]"#,
+        )
+        .unwrap();
+        let summary = Summary::new(
+            &[draft, reviewed, pull_request("Other", "")],
+            "jane@contoso.com",
+        );
+        assert_eq!(
+            summary.line(StatusFilter::Active),
+            "3 open, 1 draft, 1 awaiting your review"
+        );
+    }
+
+    #[test]
+    fn repos_is_a_command_with_or_without_arguments() {
+        for args in [
+            &["prlist", "repos"][..],
+            &["prlist", "pat", "jane", "contoso", "webshop", "repos"],
+        ] {
+            let options = Options::try_parse_from(args).unwrap();
+            assert!(matches!(options.command, Some(Command::Repos)));
+        }
+        let options = Options::try_parse_from(["prlist", "pat", "jane"]).unwrap();
+        assert!(options.command.is_none());
+    }
+
+    #[test]
+    fn broken_pipes_are_recognized_through_context() {
+        let error = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::BrokenPipe))
+            .context("Failed to write to stdout");
+        assert!(is_broken_pipe(&error));
+        let error = anyhow::Error::new(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(!is_broken_pipe(&error));
+    }
+
+    #[test]
+    fn parses_dates_and_times() {
+        assert_eq!(
+            parse_date("2024-05-01").unwrap().to_rfc3339(),
+            "2024-05-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_date("2024-05-01T12:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-05-01T10:30:00+00:00"
+        );
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("May 1st").is_err());
+    }
+
+    #[test]
+    fn parses_short_durations() {
+        assert_eq!(parse_duration("7d"), Ok(Duration::days(7)));
+        assert_eq!(parse_duration("24h"), Ok(Duration::hours(24)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::minutes(30)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::weeks(2)));
+    }
+
+    #[test]
+    fn parses_durations_leniently() {
+        assert_eq!(parse_duration(" 3 Days "), Ok(Duration::days(3)));
+        assert_eq!(parse_duration("12 hours"), Ok(Duration::hours(12)));
+        assert_eq!(parse_duration("5"), Ok(Duration::days(5)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("7 fortnights").is_err());
+        assert!(parse_duration("").is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use prlist::PullRequest;
+
+const PLACEHOLDERS: [(&str, Placeholder); 12] = [
+    ("id", Placeholder::Id),
+    ("author", Placeholder::Author),
+    ("title", Placeholder::Title),
+    ("url", Placeholder::Url),
+    ("branch", Placeholder::Branch),
+    ("source", Placeholder::Source),
+    ("target", Placeholder::Target),
+    ("date", Placeholder::Date),
+    ("status", Placeholder::Status),
+    ("repository", Placeholder::Repository),
+    ("reviewers", Placeholder::Reviewers),
+    ("work_items", Placeholder::WorkItems),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    Id,
+    Author,
+    Title,
+    Url,
+    Branch,
+    Source,
+    Target,
+    Date,
+    Status,
+    Repository,
+    Reviewers,
+    WorkItems,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// A line of output per pull request, like `{id} {author}: {title}`. Use
+/// `{{` and `}}` for literal braces.
+#[derive(Clone, Debug)]
+pub struct Template(Vec<Part>);
+
+impl Template {
+    pub fn parse(s: &str) -> Result<Template, String> {
+        let mut parts = vec![];
+        let mut text = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| format!("unclosed placeholder in {s:?}"))?;
+                    let name = &rest[..end];
+                    let placeholder = PLACEHOLDERS
+                        .iter()
+                        .find(|(n, _)| *n == name)
+                        .map(|(_, placeholder)| *placeholder)
+                        .ok_or_else(|| {
+                            let known: Vec<&str> = PLACEHOLDERS.iter().map(|(n, _)| *n).collect();
+                            format!(
+                                "unknown placeholder {{{name}}}, expected one of {}",
+                                known.join(", ")
+                            )
+                        })?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Placeholder(placeholder));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err(format!("unmatched }} in {s:?}")),
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template(parts))
+    }
+
+    pub fn render(&self, pr: &PullRequest, url: &str) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => text.clone(),
+                Part::Placeholder(placeholder) => value(*placeholder, pr, url),
+            })
+            .collect()
+    }
+}
+
+fn value(placeholder: Placeholder, pr: &PullRequest, url: &str) -> String {
+    match placeholder {
+        Placeholder::Id => pr.pull_request_id.to_string(),
+        Placeholder::Author => prlist::text::sanitize(&pr.created_by.display_name),
+        Placeholder::Title => prlist::text::sanitize(pr.title.trim_end()).replace('\n', " "),
+        Placeholder::Url => url.to_string(),
+        Placeholder::Branch => format!("{} → {}", pr.source_branch(), pr.target_branch()),
+        Placeholder::Source => pr.source_branch().to_string(),
+        Placeholder::Target => pr.target_branch().to_string(),
+        Placeholder::Date => pr.creation_date.format("%Y-%m-%d %H:%M").to_string(),
+        Placeholder::Status => pr.status.clone(),
+        Placeholder::Repository => pr.repository.name.clone(),
+        Placeholder::Reviewers => pr
+            .reviewers
+            .iter()
+            .map(|reviewer| prlist::text::sanitize(&reviewer.person.display_name))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Placeholder::WorkItems => pr
+            .work_item_refs
+            .iter()
+            .map(|work_item| format!("#{}", work_item.id))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::pull_request;
+
+    #[test]
+    fn renders_placeholders_and_text() {
+        let pr = pull_request("Add widgets", "");
+        let template = Template::parse("{{{id}}} {author}: {title} ({branch})").unwrap();
+        assert_eq!(
+            template.render(&pr, "https://example.com"),
+            "{1} Mallory: Add widgets (evil → main)"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_and_unclosed_placeholders() {
+        assert!(Template::parse("{id} {nope}")
+            .unwrap_err()
+            .contains("{nope}"));
+        assert!(Template::parse("{id").is_err());
+        assert!(Template::parse("id}").is_err());
+    }
+}
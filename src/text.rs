@@ -1,97 +1,956 @@
+use std::borrow::Cow;
 use std::mem;
 
+/// A block in a description. The text is a `String` as returned by [parse],
+/// or a `Cow<str>` borrowing from the description as returned by
+/// [parse_borrowed].
 #[derive(Debug, PartialEq, Eq)]
-pub enum TextElement {
-    Paragraph(String),
-    ListEntry(String),
+pub enum TextElement<S = String> {
+    Paragraph(S),
+    ListEntry(usize, S),
+    OrderedEntry(u32, S),
+    CodeBlock(S),
+    Quote(S),
+    /// The lines between the `---` lines of front matter at the very start.
+    Metadata(S),
+    /// The cells of each row of a table, starting with the header row.
+    Table(Vec<Vec<S>>),
 }
 
+impl<S: AsRef<str>> TextElement<S> {
+    /// True if the element has no visible text.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            TextElement::Paragraph(text)
+            | TextElement::ListEntry(_, text)
+            | TextElement::OrderedEntry(_, text)
+            | TextElement::CodeBlock(text)
+            | TextElement::Quote(text)
+            | TextElement::Metadata(text) => text.as_ref().trim().is_empty(),
+            TextElement::Table(rows) => rows
+                .iter()
+                .flatten()
+                .all(|cell| cell.as_ref().trim().is_empty()),
+        }
+    }
+}
+
+impl<S> TextElement<S> {
+    /// Converts the text of the element with `f`.
+    pub fn map<T>(self, mut f: impl FnMut(S) -> T) -> TextElement<T> {
+        match self {
+            TextElement::Paragraph(text) => TextElement::Paragraph(f(text)),
+            TextElement::ListEntry(depth, text) => TextElement::ListEntry(depth, f(text)),
+            TextElement::OrderedEntry(n, text) => TextElement::OrderedEntry(n, f(text)),
+            TextElement::CodeBlock(text) => TextElement::CodeBlock(f(text)),
+            TextElement::Quote(text) => TextElement::Quote(f(text)),
+            TextElement::Metadata(text) => TextElement::Metadata(f(text)),
+            TextElement::Table(rows) => TextElement::Table(
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(&mut f).collect())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl TextElement<Cow<'_, str>> {
+    pub fn into_owned(self) -> TextElement {
+        self.map(Cow::into_owned)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Span {
+    Plain(String),
+    Bold(String),
+    Italic(String),
+    /// The text and the URL of a `[text](url)` link.
+    Link(String, String),
+    /// The literal content of a `` `code` `` span.
+    Code(String),
+}
+
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
 pub fn parse(raw: &str) -> Vec<TextElement> {
-    enum State {
+    parse_with_tab_width(raw, DEFAULT_TAB_WIDTH)
+}
+
+pub fn parse_with_tab_width(raw: &str, tab_width: usize) -> Vec<TextElement> {
+    parse_borrowed_with_tab_width(raw, tab_width)
+        .into_iter()
+        .map(TextElement::into_owned)
+        .collect()
+}
+
+/// Like [parse], but the elements borrow their text from `raw` where it is
+/// used as is, like in paragraphs and list entries on a single line.
+pub fn parse_borrowed(raw: &str) -> Vec<TextElement<Cow<'_, str>>> {
+    parse_borrowed_with_tab_width(raw, DEFAULT_TAB_WIDTH)
+}
+
+pub fn parse_borrowed_with_tab_width(
+    raw: &str,
+    tab_width: usize,
+) -> Vec<TextElement<Cow<'_, str>>> {
+    // Descriptions often have Windows line endings.
+    if raw.contains('\r') {
+        let normalized = raw.replace("\r\n", "\n").replace('\r', "\n");
+        return parse_normalized(&normalized, tab_width)
+            .into_iter()
+            .map(|element| element.map(|text| Cow::Owned(text.into_owned())))
+            .collect();
+    }
+    parse_normalized(raw, tab_width)
+}
+
+fn parse_normalized(raw: &str, tab_width: usize) -> Vec<TextElement<Cow<'_, str>>> {
+    match front_matter(raw) {
+        Some((metadata, rest)) => {
+            let mut result = vec![TextElement::Metadata(Cow::Borrowed(metadata))];
+            result.extend(parse_tables(rest, tab_width));
+            result
+        }
+        None => parse_tables(raw, tab_width),
+    }
+}
+
+/// Parses the tables in `raw`, and the text around them with [parse_blocks].
+/// A table starts with a line with `|` followed by a separator line like
+/// `---|---` with as many columns, and goes on for as long as there are
+/// lines with `|`. Lines in fenced code blocks are never part of a table.
+fn parse_tables(raw: &str, tab_width: usize) -> Vec<TextElement<Cow<'_, str>>> {
+    let mut lines = vec![];
+    let mut start = 0;
+    for line in raw.split_inclusive('\n') {
+        lines.push((start, line));
+        start += line.len();
+    }
+
+    let mut result = vec![];
+    let mut text_start = 0;
+    let mut in_fence = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let (start, line) = lines[i];
+        if is_fence(line) {
+            in_fence = !in_fence;
+        }
+        let header = match (in_fence, lines.get(i + 1)) {
+            (false, Some((_, next))) => {
+                table_row(line).filter(|header| is_table_separator(next, header.len()))
+            }
+            _ => None,
+        };
+        let Some(header) = header else {
+            i += 1;
+            continue;
+        };
+        result.extend(parse_blocks(&raw[text_start..start], tab_width));
+        let mut rows = vec![header];
+        i += 2;
+        while let Some(row) = lines.get(i).and_then(|(_, line)| table_row(line)) {
+            rows.push(row);
+            i += 1;
+        }
+        result.push(TextElement::Table(rows));
+        text_start = lines.get(i).map_or(raw.len(), |(start, _)| *start);
+    }
+    result.extend(parse_blocks(&raw[text_start..], tab_width));
+    result
+}
+
+/// The cells of a table row, split at `|` but not `\|`, without the pipes
+/// at the start and end of the line.
+fn table_row(line: &str) -> Option<Vec<Cow<'_, str>>> {
+    let line = line.trim();
+    if !line.contains('|') {
+        return None;
+    }
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+    let mut cells = vec![];
+    let mut cell_start = 0;
+    let mut escaped = false;
+    for (at, c) in line.char_indices() {
+        match c {
+            '|' if !escaped => {
+                cells.push(Cow::Borrowed(line[cell_start..at].trim()));
+                cell_start = at + 1;
+            }
+            _ => escaped = c == '\\' && !escaped,
+        }
+    }
+    cells.push(Cow::Borrowed(line[cell_start..].trim()));
+    Some(cells)
+}
+
+/// True for a line like `---|:---:` with `columns` columns.
+fn is_table_separator(line: &str, columns: usize) -> bool {
+    match table_row(line) {
+        Some(cells) => {
+            cells.len() == columns
+                && cells.iter().all(|cell| {
+                    let dashes = cell.strip_prefix(':').unwrap_or(cell);
+                    let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+                    !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+                })
+        }
+        None => false,
+    }
+}
+
+/// The front matter at the start of `raw`, between a `---` line and the
+/// next one, and the text after it.
+fn front_matter(raw: &str) -> Option<(&str, &str)> {
+    let body = raw.strip_prefix("---\n")?;
+    let mut end = 0;
+    for line in body.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            return Some((
+                body[..end].trim_end_matches('\n'),
+                &body[end + line.len()..],
+            ));
+        }
+        end += line.len();
+    }
+    None
+}
+
+fn parse_blocks(raw: &str, tab_width: usize) -> Vec<TextElement<Cow<'_, str>>> {
+    enum State<'a> {
         Init,
-        InParagraph { text: String, last: char },
-        InListEntry { text: String, text_started: bool },
+        InParagraph {
+            text: Fragment<'a>,
+            last: char,
+        },
+        InListEntry {
+            text: Fragment<'a>,
+            text_started: bool,
+            number: Option<u32>,
+            depth: usize,
+            line_ended: bool,
+        },
+        InNumber {
+            digits: Fragment<'a>,
+            paragraph: Option<Fragment<'a>>,
+        },
+        InMarker {
+            marker: Fragment<'a>,
+            number: Option<u32>,
+            paragraph: Option<Fragment<'a>>,
+        },
+        InFence {
+            ticks: Fragment<'a>,
+            paragraph: Option<Fragment<'a>>,
+        },
+        InCodeBlock {
+            code: Fragment<'a>,
+            line: Fragment<'a>,
+            opening: bool,
+        },
+        InQuote {
+            text: Fragment<'a>,
+            last: char,
+        },
+    }
+
+    /// The state after `c` at `at`, the first character on a line that isn't
+    /// part of a paragraph or list entry.
+    fn start_line(raw: &str, at: usize, c: char) -> State<'_> {
+        let fragment = Fragment::starting_with(raw, at, c);
+        match c {
+            '-' | '*' | '+' | '>' => State::InMarker {
+                marker: fragment,
+                number: None,
+                paragraph: None,
+            },
+            '0'..='9' => State::InNumber {
+                digits: fragment,
+                paragraph: None,
+            },
+            '`' => State::InFence {
+                ticks: fragment,
+                paragraph: None,
+            },
+            _ => State::InParagraph {
+                text: fragment,
+                last: c,
+            },
+        }
+    }
+
+    fn resume_paragraph<'a>(
+        paragraph: Option<Fragment<'a>>,
+        prefix: Fragment<'a>,
+        at: usize,
+        c: char,
+    ) -> State<'a> {
+        let mut text = paragraph_text(paragraph, prefix);
+        let last = if c == '\n' {
+            '\n'
+        } else {
+            text.push(at, c);
+            c
+        };
+        State::InParagraph { text, last }
     }
 
     let mut state = State::Init;
     let mut result = vec![];
-    for c in raw.chars() {
+    let mut indent = 0;
+    for (at, c) in raw.char_indices() {
+        if c == '\n' {
+            indent = 0;
+        }
         match state {
             State::Init => match c {
-                '\n' | ' ' => {}
-                '-' | '*' => {
-                    state = State::InListEntry {
-                        text: String::new(),
-                        text_started: false,
-                    }
-                }
-                _ => {
-                    state = State::InParagraph {
-                        text: c.into(),
-                        last: c,
-                    }
-                }
+                '\n' => {}
+                ' ' => indent += 1,
+                '\t' => indent += tab_width,
+                _ => state = start_line(raw, at, c),
             },
             State::InParagraph {
                 text: ref mut s,
                 ref mut last,
             } => match c {
                 '\n' if *last == '\n' => {
-                    result.push(TextElement::Paragraph(mem::take(s)));
+                    result.push(TextElement::Paragraph(s.take().into_cow()));
                     state = State::Init;
                 }
                 '\n' => {
                     *last = '\n';
                 }
-                ' ' if *last == '\n' => {}
-                '-' | '*' if *last == '\n' => {
-                    result.push(TextElement::Paragraph(mem::take(s)));
-                    state = State::InListEntry {
-                        text: String::new(),
-                        text_started: false,
+                ' ' if *last == '\n' => indent += 1,
+                '\t' if *last == '\n' => indent += tab_width,
+                '-' | '*' | '+' | '>' if *last == '\n' => {
+                    state = State::InMarker {
+                        marker: Fragment::starting_with(raw, at, c),
+                        number: None,
+                        paragraph: Some(s.take()),
+                    }
+                }
+                '0'..='9' if *last == '\n' => {
+                    state = State::InNumber {
+                        digits: Fragment::starting_with(raw, at, c),
+                        paragraph: Some(s.take()),
+                    }
+                }
+                '`' if *last == '\n' => {
+                    state = State::InFence {
+                        ticks: Fragment::starting_with(raw, at, c),
+                        paragraph: Some(s.take()),
                     }
                 }
                 _ => {
                     if *last == '\n' {
-                        s.push(' ');
+                        s.push_copied(' ');
                     }
-                    s.push(c);
+                    s.push(at, c);
                     *last = c;
                 }
             },
             State::InListEntry {
                 ref mut text,
                 ref mut text_started,
-            } => {
-                match c {
-                    '\n' => {
-                        result.push(TextElement::ListEntry(mem::take(text)));
-                        state = State::Init;
+                number,
+                depth,
+                ref mut line_ended,
+            } => match c {
+                '\n' if *line_ended => {
+                    result.extend(list_entry(text.take(), number, depth));
+                    state = State::Init;
+                }
+                '\n' => *line_ended = true,
+                ' ' if *line_ended => indent += 1,
+                '\t' if *line_ended => indent += tab_width,
+                // An indented line that doesn't start a new block continues
+                // the entry.
+                _ if *line_ended && indent > 0 && !starts_block(&raw[at..]) => {
+                    text.push_copied(' ');
+                    text.push(at, c);
+                    *line_ended = false;
+                }
+                _ if *line_ended => {
+                    result.extend(list_entry(text.take(), number, depth));
+                    state = start_line(raw, at, c);
+                }
+                _ if *text_started => text.push(at, c),
+                _ if c.is_whitespace() => {}
+                _ => {
+                    *text_started = true;
+                    text.push(at, c);
+                }
+            },
+            State::InNumber {
+                ref mut digits,
+                ref mut paragraph,
+            } => match c {
+                '0'..='9' => digits.push(at, c),
+                '.' | ')' if digits.as_str().parse::<u32>().is_ok() => {
+                    let number = digits.as_str().parse().ok();
+                    let mut marker = digits.take();
+                    marker.push(at, c);
+                    state = State::InMarker {
+                        marker,
+                        number,
+                        paragraph: paragraph.take(),
                     }
-                    _ if *text_started => {
-                        text.push(c);
+                }
+                _ => state = resume_paragraph(paragraph.take(), digits.take(), at, c),
+            },
+            State::InMarker {
+                ref mut marker,
+                number,
+                ref mut paragraph,
+            } => match c {
+                ' ' | '\t' if marker.as_str() == ">" => {
+                    if let Some(p) = paragraph.take() {
+                        result.push(TextElement::Paragraph(p.into_cow()));
                     }
-                    _ if c.is_whitespace() => {}
-                    _ => {
-                        *text_started = true;
-                        text.push(c);
+                    state = State::InQuote {
+                        text: Fragment::new(raw),
+                        last: '>',
                     }
-                };
-            }
+                }
+                ' ' | '\t' => {
+                    if let Some(p) = paragraph.take() {
+                        result.push(TextElement::Paragraph(p.into_cow()));
+                    }
+                    state = State::InListEntry {
+                        text: Fragment::new(raw),
+                        text_started: false,
+                        number,
+                        depth: indent,
+                        line_ended: false,
+                    }
+                }
+                _ => state = resume_paragraph(paragraph.take(), marker.take(), at, c),
+            },
+            State::InFence {
+                ref mut ticks,
+                ref mut paragraph,
+            } => match c {
+                '`' => {
+                    ticks.push(at, c);
+                    if ticks.as_str().len() == 3 {
+                        if let Some(p) = paragraph.take() {
+                            result.push(TextElement::Paragraph(p.into_cow()));
+                        }
+                        state = State::InCodeBlock {
+                            code: Fragment::new(raw),
+                            line: Fragment::new(raw),
+                            opening: true,
+                        }
+                    }
+                }
+                _ => state = resume_paragraph(paragraph.take(), ticks.take(), at, c),
+            },
+            State::InCodeBlock {
+                ref mut code,
+                ref mut line,
+                ref mut opening,
+            } => match c {
+                '\n' if *opening => *opening = false,
+                _ if *opening => {}
+                '\n' if is_fence(line.as_str()) => {
+                    result.push(code_block(code.take()));
+                    state = State::Init;
+                }
+                '\n' => {
+                    code.append(line.take());
+                    code.push(at, c);
+                }
+                _ => line.push(at, c),
+            },
+            // Like in CommonMark, lines without the `>` marker continue the
+            // quote until there is a blank line.
+            State::InQuote {
+                ref mut text,
+                ref mut last,
+            } => match c {
+                '\n' if *last == '\n' => {
+                    result.push(TextElement::Quote(text.take().into_cow()));
+                    state = State::Init;
+                }
+                '\n' => *last = '\n',
+                '>' if *last == '\n' => *last = '>',
+                ' ' | '\t' if *last == '\n' || *last == '>' => {}
+                _ => {
+                    if (*last == '\n' || *last == '>') && !text.is_empty() {
+                        text.push_copied(' ');
+                    }
+                    text.push(at, c);
+                    *last = c;
+                }
+            },
         }
     }
     match state {
         State::Init => {}
-        State::InParagraph { text, .. } => result.push(TextElement::Paragraph(text)),
-        State::InListEntry { text, .. } => result.push(TextElement::ListEntry(text)),
+        State::InParagraph { text, .. } => result.push(TextElement::Paragraph(text.into_cow())),
+        State::InListEntry {
+            text,
+            number,
+            depth,
+            ..
+        } => result.extend(list_entry(text, number, depth)),
+        State::InNumber { digits, paragraph } => result.push(TextElement::Paragraph(
+            paragraph_text(paragraph, digits).into_cow(),
+        )),
+        State::InMarker {
+            marker, paragraph, ..
+        } => result.push(TextElement::Paragraph(
+            paragraph_text(paragraph, marker).into_cow(),
+        )),
+        State::InFence { ticks, paragraph } => result.push(TextElement::Paragraph(
+            paragraph_text(paragraph, ticks).into_cow(),
+        )),
+        State::InCodeBlock { mut code, line, .. } => {
+            if !is_fence(line.as_str()) {
+                code.append(line);
+            }
+            result.push(code_block(code))
+        }
+        State::InQuote { text, .. } => result.push(TextElement::Quote(text.into_cow())),
+    }
+
+    result
+}
+
+/// Text collected by the parser. It borrows from the input for as long as
+/// the characters pushed are consecutive there, and becomes a copy when
+/// something else is added.
+struct Fragment<'a> {
+    raw: &'a str,
+    start: usize,
+    end: usize,
+    copy: Option<String>,
+}
+
+impl<'a> Fragment<'a> {
+    fn new(raw: &'a str) -> Self {
+        Fragment {
+            raw,
+            start: 0,
+            end: 0,
+            copy: None,
+        }
+    }
+
+    fn starting_with(raw: &'a str, at: usize, c: char) -> Self {
+        let mut fragment = Fragment::new(raw);
+        fragment.push(at, c);
+        fragment
+    }
+
+    /// Adds `c`, found at `at` in the input.
+    fn push(&mut self, at: usize, c: char) {
+        if self.copy.is_some() {
+            self.push_copied(c);
+        } else if self.start == self.end {
+            self.start = at;
+            self.end = at + c.len_utf8();
+        } else if self.end == at {
+            self.end += c.len_utf8();
+        } else {
+            self.push_copied(c);
+        }
+    }
+
+    /// Adds `c`, which isn't taken from the input.
+    fn push_copied(&mut self, c: char) {
+        self.copied().push(c);
+    }
+
+    fn append(&mut self, other: Fragment<'a>) {
+        match (&self.copy, &other.copy) {
+            _ if other.is_empty() => {}
+            (None, None) if self.start == self.end => *self = other,
+            (None, None) if self.end == other.start => self.end = other.end,
+            _ => self.copied().push_str(other.as_str()),
+        }
+    }
+
+    fn pop_newline(&mut self) {
+        if self.as_str().ends_with('\n') {
+            match &mut self.copy {
+                Some(copy) => {
+                    copy.pop();
+                }
+                None => self.end -= 1,
+            }
+        }
+    }
+
+    fn take(&mut self) -> Fragment<'a> {
+        mem::replace(self, Fragment::new(self.raw))
+    }
+
+    fn copied(&mut self) -> &mut String {
+        let borrowed = &self.raw[self.start..self.end];
+        self.copy.get_or_insert_with(|| borrowed.to_string())
+    }
+
+    fn as_str(&self) -> &str {
+        match &self.copy {
+            Some(copy) => copy,
+            None => &self.raw[self.start..self.end],
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.as_str().is_empty()
+    }
+
+    fn into_cow(self) -> Cow<'a, str> {
+        match self.copy {
+            Some(copy) => Cow::Owned(copy),
+            None => Cow::Borrowed(&self.raw[self.start..self.end]),
+        }
+    }
+}
+
+/// The list entry with `text`, or nothing if there is only a marker.
+fn list_entry(text: Fragment, number: Option<u32>, depth: usize) -> Option<TextElement<Cow<str>>> {
+    if text.as_str().trim().is_empty() {
+        return None;
+    }
+    Some(match number {
+        Some(n) => TextElement::OrderedEntry(n, text.into_cow()),
+        None => TextElement::ListEntry(depth, text.into_cow()),
+    })
+}
+
+/// Splits the text of a paragraph or list entry into plain, emphasized,
+/// link and code spans. `**bold**`/`__bold__`, `*italic*`/`_italic_`,
+/// `[text](url)` and `` `code` `` are recognized, nested emphasis is not.
+pub fn spans(text: &str) -> Vec<Span> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = vec![];
+    let mut plain = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match code(&chars, i)
+            .or_else(|| link(&chars, i))
+            .or_else(|| emphasis(&chars, i))
+        {
+            Some((span, end)) => {
+                if !plain.is_empty() {
+                    result.push(Span::Plain(mem::take(&mut plain)));
+                }
+                result.push(span);
+                i = end;
+            }
+            // Backticks that don't start a code span are kept together, so
+            // that a part of them can't start one either.
+            None if chars[i] == '`' => {
+                let ticks = backticks(&chars, i);
+                plain.extend(&chars[i..i + ticks]);
+                i += ticks;
+            }
+            None => {
+                plain.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if !plain.is_empty() {
+        result.push(Span::Plain(plain));
+    }
+    result
+}
+
+/// The code span starting at `start`, if any, and where it ends. Like in
+/// CommonMark, it ends with as many backticks as it starts with, and a space
+/// at both ends of the content is removed.
+fn code(chars: &[char], start: usize) -> Option<(Span, usize)> {
+    if chars[start] != '`' {
+        return None;
+    }
+    let ticks = backticks(chars, start);
+    let content_start = start + ticks;
+    let mut i = content_start;
+    while i < chars.len() {
+        if chars[i] != '`' {
+            i += 1;
+            continue;
+        }
+        let closing = backticks(chars, i);
+        if closing == ticks {
+            let mut content = &chars[content_start..i];
+            if content.len() >= 2
+                && content[0] == ' '
+                && content[content.len() - 1] == ' '
+                && content.iter().any(|&c| c != ' ')
+            {
+                content = &content[1..content.len() - 1];
+            }
+            return Some((Span::Code(content.iter().collect()), i + closing));
+        }
+        i += closing;
+    }
+    None
+}
+
+fn backticks(chars: &[char], start: usize) -> usize {
+    chars[start..].iter().take_while(|&&c| c == '`').count()
+}
+
+/// The link starting at `start`, if any, and where it ends. Brackets in the
+/// text must be balanced or escaped with a backslash.
+fn link(chars: &[char], start: usize) -> Option<(Span, usize)> {
+    if chars[start] != '[' {
+        return None;
+    }
+    let mut text = String::new();
+    let mut depth = 0;
+    let mut i = start + 1;
+    loop {
+        match *chars.get(i)? {
+            '\\' if matches!(chars.get(i + 1), Some('[' | ']')) => {
+                text.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            ']' if depth == 0 => break,
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        text.push(chars[i]);
+        i += 1;
+    }
+    if chars.get(i + 1) != Some(&'(') {
+        return None;
+    }
+    let url_start = i + 2;
+    let url_end = url_start + chars[url_start..].iter().position(|&c| c == ')')?;
+    let url: String = chars[url_start..url_end].iter().collect();
+    if text.is_empty() || url.is_empty() || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((Span::Link(text, url), url_end + 1))
+}
+
+fn emphasis(chars: &[char], start: usize) -> Option<(Span, usize)> {
+    let marker = chars[start];
+    if marker != '*' && marker != '_' {
+        return None;
+    }
+    if marker == '_' && start > 0 && chars[start - 1].is_alphanumeric() {
+        return None;
+    }
+    let width = if chars.get(start + 1) == Some(&marker) {
+        2
+    } else {
+        1
+    };
+    let content_start = start + width;
+    if chars.get(content_start).is_none_or(|c| c.is_whitespace()) {
+        return None;
     }
+    let delimiter = &chars[start..content_start];
+    let content_end = (content_start + 1..=chars.len() - width).find(|&i| {
+        &chars[i..i + width] == delimiter
+            && !chars[i - 1].is_whitespace()
+            && (marker != '_' || chars.get(i + width).is_none_or(|c| !c.is_alphanumeric()))
+    })?;
+    let content = chars[content_start..content_end].iter().collect();
+    let span = if width == 2 {
+        Span::Bold(content)
+    } else {
+        Span::Italic(content)
+    };
+    Some((span, content_end + width))
+}
 
+/// Makes `raw` safe to print to a terminal. Carriage returns are dropped
+/// and control characters other than newlines and tabs are escaped, so
+/// they can't move the cursor or change colors.
+pub fn sanitize(raw: &str) -> String {
+    let mut result = String::with_capacity(raw.len());
+    for c in raw.chars() {
+        match c {
+            '\r' => {}
+            '\n' | '\t' => result.push(c),
+            _ if c.is_control() => result.extend(c.escape_default()),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Replaces GitHub-style shortcodes like `:rocket:` with their emoji.
+/// Unknown shortcodes are left as they are.
+pub fn emojify(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let emoji = after
+            .find(':')
+            .filter(|&end| is_shortcode(&after[..end]))
+            .and_then(|end| Some((emojis::get_by_shortcode(&after[..end])?, end)));
+        match emoji {
+            Some((emoji, end)) => {
+                result.push_str(emoji.as_str());
+                rest = &after[end + 1..];
+            }
+            None => {
+                result.push(':');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
     result
 }
 
+fn is_shortcode(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-'))
+}
+
+/// Writes `elements` back as markdown that [parse] reads as the same
+/// elements. Entries of a list are on consecutive lines, other blocks are
+/// separated by a blank line.
+pub fn to_markdown<S: AsRef<str>>(elements: &[TextElement<S>]) -> String {
+    let mut result = String::new();
+    let mut previous_was_entry = false;
+    for element in elements {
+        let is_entry = matches!(
+            element,
+            TextElement::ListEntry(..) | TextElement::OrderedEntry(..)
+        );
+        if !result.is_empty() {
+            result.push_str(if previous_was_entry && is_entry {
+                "\n"
+            } else {
+                "\n\n"
+            });
+        }
+        previous_was_entry = is_entry;
+        match element {
+            TextElement::Paragraph(text) => result.push_str(text.as_ref()),
+            TextElement::ListEntry(depth, text) => {
+                result.push_str(&format!("{:depth$}- {}", "", text.as_ref()))
+            }
+            TextElement::OrderedEntry(n, text) => {
+                result.push_str(&format!("{n}. {}", text.as_ref()))
+            }
+            TextElement::CodeBlock(code) => {
+                result.push_str(&format!("```\n{}\n```", code.as_ref()))
+            }
+            TextElement::Quote(text) => result.push_str(&format!("> {}", text.as_ref())),
+            TextElement::Metadata(text) => result.push_str(&format!("---\n{}\n---", text.as_ref())),
+            TextElement::Table(rows) => {
+                let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+                let mut lines: Vec<String> = rows
+                    .iter()
+                    .map(|row| {
+                        let cells: Vec<&str> = row.iter().map(AsRef::as_ref).collect();
+                        format!("| {} |", cells.join(" | "))
+                    })
+                    .collect();
+                lines.insert(1, format!("|{}", "---|".repeat(columns)));
+                result.push_str(&lines.join("\n"));
+            }
+        }
+    }
+    if !result.is_empty() {
+        result.push('\n');
+    }
+    result
+}
+
+/// Whether `text` starts with the marker of a list entry or quote followed
+/// by whitespace, or with a code fence.
+fn starts_block(text: &str) -> bool {
+    if text.starts_with("```") {
+        return true;
+    }
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let after_marker = if digits > 0 {
+        text[digits..].strip_prefix(['.', ')'])
+    } else {
+        text.strip_prefix(['-', '*', '+', '>'])
+    };
+    after_marker.is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("```") && line.chars().all(|c| c == '`')
+}
+
+fn code_block(mut code: Fragment) -> TextElement<Cow<str>> {
+    code.pop_newline();
+    TextElement::CodeBlock(code.into_cow())
+}
+
+fn paragraph_text<'a>(paragraph: Option<Fragment<'a>>, text: Fragment<'a>) -> Fragment<'a> {
+    match paragraph {
+        Some(mut p) => {
+            p.push_copied(' ');
+            p.append(text);
+            p
+        }
+        None => text,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
+
+    fn text_length(elements: &[TextElement]) -> usize {
+        elements
+            .iter()
+            .map(|element| match element {
+                TextElement::Paragraph(text)
+                | TextElement::ListEntry(_, text)
+                | TextElement::OrderedEntry(_, text)
+                | TextElement::CodeBlock(text)
+                | TextElement::Quote(text)
+                | TextElement::Metadata(text) => text.len(),
+                TextElement::Table(rows) => rows.iter().flatten().map(String::len).sum(),
+            })
+            .sum()
+    }
+
+    proptest! {
+        #[test]
+        fn parse_handles_any_input(raw in any::<String>()) {
+            let elements = parse(&raw);
+            prop_assert!(text_length(&elements) <= raw.len());
+        }
+
+        #[test]
+        fn parse_handles_markdown_like_input(
+            raw in proptest::string::string_regex(
+                "([ \t\r\n`*_>+.\\\\\\[\\]()-]|[0-9]|[aé😀]|---\n|```\n){0,120}"
+            ).unwrap()
+        ) {
+            let elements = parse(&raw);
+            prop_assert!(text_length(&elements) <= raw.len());
+            for element in &elements {
+                if let TextElement::Paragraph(text) | TextElement::ListEntry(_, text) = element {
+                    spans(text);
+                }
+            }
+        }
+    }
 
     #[test]
     fn single_line_is_a_single_paragraph() {
@@ -101,6 +960,94 @@ mod test {
         assert_eq!(result[0], TextElement::Paragraph(input.into()));
     }
 
+    #[test]
+    fn front_matter_is_metadata() {
+        let input = "---\nrisk: low\narea: billing\n---\nFixes the totals.\n";
+        assert_eq!(
+            parse(input),
+            vec![
+                TextElement::Metadata("risk: low\narea: billing".into()),
+                TextElement::Paragraph("Fixes the totals.".into()),
+            ]
+        );
+        assert!(matches!(
+            parse_borrowed(input)[0],
+            TextElement::Metadata(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn dashes_without_closing_line_are_not_front_matter() {
+        let result = parse("---\nrisk: low\n\nFixes the totals.");
+        assert!(!result
+            .iter()
+            .any(|element| matches!(element, TextElement::Metadata(_))));
+        assert_eq!(
+            result.last(),
+            Some(&TextElement::Paragraph("Fixes the totals.".into()))
+        );
+        assert!(!parse("Fixes the totals.\n\n---\nrisk: low\n---\n")
+            .iter()
+            .any(|element| matches!(element, TextElement::Metadata(_))));
+    }
+
+    #[test]
+    fn markdown_is_parsed_back_to_the_same_elements() {
+        let input = "---\nrisk: low\n---\nAdds **widgets**\nto the shop.\n\
+                     Changes:\n- one\n  - nested\n1. first\n2. second\n\n\
+                     > quoted\n\n```\nlet x = 1;\n```\nDone.";
+        let elements = parse(input);
+        let markdown = to_markdown(&elements);
+        assert_eq!(
+            markdown,
+            "---\nrisk: low\n---\n\nAdds **widgets** to the shop. Changes:\n\n\
+             - one\n  - nested\n1. first\n2. second\n\n> quoted\n\n\
+             ```\nlet x = 1;\n```\n\nDone.\n"
+        );
+        assert_eq!(parse(&markdown), elements);
+    }
+
+    #[test]
+    fn pipe_table_with_separator_is_a_table() {
+        let input = "Timings:\n| Step | Time |\n|------|-----:|\n| build | 3 s |\n| test | 12 s |\nThat's it.";
+        let elements = parse(input);
+        assert_eq!(parse(&to_markdown(&elements)), elements);
+        assert_eq!(
+            elements,
+            vec![
+                TextElement::Paragraph("Timings:".into()),
+                TextElement::Table(vec![
+                    vec!["Step".into(), "Time".into()],
+                    vec!["build".into(), "3 s".into()],
+                    vec!["test".into(), "12 s".into()],
+                ]),
+                TextElement::Paragraph("That's it.".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn pipes_without_separator_are_not_a_table() {
+        let input = "Use a | b to pipe.\nOr c | d.\n\n```\n| a | b |\n|---|---|\n```";
+        assert!(!parse(input)
+            .iter()
+            .any(|element| matches!(element, TextElement::Table(_))));
+        assert!(!parse("| a | b |\n|---|\n")
+            .iter()
+            .any(|element| matches!(element, TextElement::Table(_))));
+    }
+
+    #[test]
+    fn escaped_pipes_stay_in_their_cell() {
+        assert_eq!(
+            parse("a | b\n--|--\nx \\| y | z"),
+            vec![TextElement::Table(vec![
+                vec!["a".into(), "b".into()],
+                vec!["x \\| y".into(), "z".into()],
+            ])]
+        );
+    }
+
     #[test]
     fn newlines_does_not_introduce_paragraphs() {
         let line1 = "line1 line1 line1 line1 line1 line1 line1 line1";
@@ -128,6 +1075,95 @@ mod test {
         assert_eq!(result[2], TextElement::Paragraph(para3.replace("\n", " ")));
     }
 
+    #[test]
+    fn crlf_line_endings_are_handled_like_newlines() {
+        for input in ["line1\r\nline2\r\n\r\npara2", "line1\rline2\r\rpara2"] {
+            let result = dbg!(parse(input));
+            assert_eq!(
+                result,
+                vec![
+                    TextElement::Paragraph("line1 line2".into()),
+                    TextElement::Paragraph("para2".into()),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn single_line_text_is_borrowed() {
+        let input = "One line\n\nTwo\nlines\n\n- entry\n\n```\ncode\nblock\n```\n";
+        let result = dbg!(parse_borrowed(input));
+        assert!(matches!(
+            result[0],
+            TextElement::Paragraph(Cow::Borrowed("One line"))
+        ));
+        assert!(matches!(result[1], TextElement::Paragraph(Cow::Owned(_))));
+        assert!(matches!(
+            result[2],
+            TextElement::ListEntry(0, Cow::Borrowed("entry"))
+        ));
+        assert!(matches!(
+            result[3],
+            TextElement::CodeBlock(Cow::Borrowed("code\nblock"))
+        ));
+        let owned: Vec<TextElement> = result.into_iter().map(TextElement::into_owned).collect();
+        assert_eq!(owned, parse(input));
+    }
+
+    #[test]
+    fn code_spans_are_not_emphasized() {
+        assert_eq!(
+            spans("Use `a*b*c` or ``x `y` z``, not *this*"),
+            vec![
+                Span::Plain("Use ".into()),
+                Span::Code("a*b*c".into()),
+                Span::Plain(" or ".into()),
+                Span::Code("x `y` z".into()),
+                Span::Plain(", not ".into()),
+                Span::Italic("this".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_backticks_are_plain() {
+        assert_eq!(
+            spans("``not code` at all"),
+            vec![Span::Plain("``not code` at all".into())]
+        );
+    }
+
+    #[test]
+    fn link_at_paragraph_start() {
+        assert_eq!(
+            spans("[The issue](https://example.com/1) explains it"),
+            vec![
+                Span::Link("The issue".into(), "https://example.com/1".into()),
+                Span::Plain(" explains it".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn link_mid_sentence() {
+        assert_eq!(
+            spans("See [the [old] docs \\]](https://example.com/docs) first."),
+            vec![
+                Span::Plain("See ".into()),
+                Span::Link("the [old] docs ]".into(), "https://example.com/docs".into()),
+                Span::Plain(" first.".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn brackets_without_url_are_plain() {
+        assert_eq!(
+            spans("[WIP] not a [link] (really)"),
+            vec![Span::Plain("[WIP] not a [link] (really)".into())]
+        );
+    }
+
     #[test]
     fn dash_at_line_start_gives_list_elements() {
         let elem1 = "- elem1 elem1 elem1";
@@ -136,9 +1172,190 @@ mod test {
         let input = format!("{elem1}\n{elem2}\n{elem3}\n");
         let result = dbg!(parse(&input));
         assert_eq!(result.len(), 3);
-        assert_eq!(result[0], TextElement::ListEntry(elem1[2..].into()));
-        assert_eq!(result[1], TextElement::ListEntry(elem2[2..].into()));
-        assert_eq!(result[2], TextElement::ListEntry(elem3[2..].into()));
+        assert_eq!(result[0], TextElement::ListEntry(0, elem1[2..].into()));
+        assert_eq!(result[1], TextElement::ListEntry(0, elem2[2..].into()));
+        assert_eq!(result[2], TextElement::ListEntry(0, elem3[2..].into()));
+    }
+
+    #[test]
+    fn indented_lines_continue_list_entries() {
+        let input = "- a bullet that is\n  wrapped in the source\n- next\nAfter";
+        let result = dbg!(parse(input));
+        assert_eq!(
+            result,
+            vec![
+                TextElement::ListEntry(0, "a bullet that is wrapped in the source".into()),
+                TextElement::ListEntry(0, "next".into()),
+                TextElement::Paragraph("After".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn continuations_may_start_like_markers() {
+        for (input, entry) in [
+            (
+                "- fixes the bug from\n  2023 and more",
+                "fixes the bug from 2023 and more",
+            ),
+            ("- calls\n  `foo()` now", "calls `foo()` now"),
+            ("- item\n  *really* important", "item *really* important"),
+            ("- costs\n  -5 points", "costs -5 points"),
+        ] {
+            assert_eq!(
+                dbg!(parse(input)),
+                vec![TextElement::ListEntry(0, entry.into())]
+            );
+        }
+        assert_eq!(
+            parse("- item\n  2. next"),
+            vec![
+                TextElement::ListEntry(0, "item".into()),
+                TextElement::OrderedEntry(2, "next".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn mixed_markers_are_all_list_items() {
+        let input = "Changes:\n- dash\n* star\n+ plus\n";
+        let result = dbg!(parse(input));
+        assert_eq!(
+            result,
+            vec![
+                TextElement::Paragraph("Changes:".into()),
+                TextElement::ListEntry(0, "dash".into()),
+                TextElement::ListEntry(0, "star".into()),
+                TextElement::ListEntry(0, "plus".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn markers_without_text_are_not_list_entries() {
+        assert_eq!(parse("- "), vec![]);
+        assert_eq!(
+            parse("Steps:\n- \n- build\n1. \n2. test\n- "),
+            vec![
+                TextElement::Paragraph("Steps:".into()),
+                TextElement::ListEntry(0, "build".into()),
+                TextElement::OrderedEntry(2, "test".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn list_entry_preserves_internal_spacing() {
+        let result = dbg!(parse("-   a   b    c\n1. d  e\n"));
+        assert_eq!(
+            result,
+            vec![
+                TextElement::ListEntry(0, "a   b    c".into()),
+                TextElement::OrderedEntry(1, "d  e".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_line_blockquote() {
+        let result = dbg!(parse("Quoting the issue:\n> It crashes on start\n"));
+        assert_eq!(
+            result,
+            vec![
+                TextElement::Paragraph("Quoting the issue:".into()),
+                TextElement::Quote("It crashes on start".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn consecutive_quote_lines_give_one_blockquote() {
+        let input = "> It crashes\n> on start\n>\n> every time\n\nAfter the quote";
+        let result = dbg!(parse(input));
+        assert_eq!(
+            result,
+            vec![
+                TextElement::Quote("It crashes on start every time".into()),
+                TextElement::Paragraph("After the quote".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn number_and_dot_at_line_start_gives_ordered_list_elements() {
+        let elem1 = "1. elem1 elem1 elem1";
+        let elem2 = "2. elem2 elem2 elem2";
+        let elem3 = "3. elem3 elem3 elem3";
+        let input = format!("{elem1}\n{elem2}\n{elem3}\n");
+        let result = dbg!(parse(&input));
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], TextElement::OrderedEntry(1, elem1[3..].into()));
+        assert_eq!(result[1], TextElement::OrderedEntry(2, elem2[3..].into()));
+        assert_eq!(result[2], TextElement::OrderedEntry(3, elem3[3..].into()));
+    }
+
+    #[test]
+    fn parenthesis_can_be_used_in_place_of_dot() {
+        let elem1 = "9) elem1 elem1 elem1";
+        let elem2 = "10) elem2 elem2 elem2";
+        let input = format!("{elem1}\n{elem2}\n");
+        let result = dbg!(parse(&input));
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0], TextElement::OrderedEntry(9, elem1[3..].into()));
+        assert_eq!(result[1], TextElement::OrderedEntry(10, elem2[4..].into()));
+    }
+
+    #[test]
+    fn number_without_marker_is_paragraph_text() {
+        let input = "Fixed\n42 bugs\n2023";
+        let result = dbg!(parse(input));
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0],
+            TextElement::Paragraph("Fixed 42 bugs 2023".into())
+        );
+    }
+
+    #[test]
+    fn ordered_list_after_paragraph() {
+        let input = "Steps:\n1. first\n2. second";
+        let result = dbg!(parse(input));
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], TextElement::Paragraph("Steps:".into()));
+        assert_eq!(result[1], TextElement::OrderedEntry(1, "first".into()));
+        assert_eq!(result[2], TextElement::OrderedEntry(2, "second".into()));
+    }
+
+    #[test]
+    fn marker_without_following_space_is_not_a_list() {
+        let result = dbg!(parse("-word"));
+        assert_eq!(result, vec![TextElement::Paragraph("-word".into())]);
+    }
+
+    #[test]
+    fn marker_with_following_space_is_a_list() {
+        let result = dbg!(parse("- word"));
+        assert_eq!(result, vec![TextElement::ListEntry(0, "word".into())]);
+    }
+
+    #[test]
+    fn hyphenated_word_wrapped_at_line_start_stays_in_paragraph() {
+        let result = dbg!(parse("this is a well\n-known fact"));
+        assert_eq!(
+            result,
+            vec![TextElement::Paragraph("this is a well -known fact".into())]
+        );
+    }
+
+    #[test]
+    fn emphasis_at_line_start_stays_in_paragraph() {
+        let result = dbg!(parse("Please note:\n*all* tests pass\n1.5 times faster"));
+        assert_eq!(
+            result,
+            vec![TextElement::Paragraph(
+                "Please note: *all* tests pass 1.5 times faster".into()
+            )]
+        );
     }
 
     #[test]
@@ -149,9 +1366,39 @@ mod test {
         let input = format!("{elem1}\n{elem2}\n{elem3}\n");
         let result = dbg!(parse(&input));
         assert_eq!(result.len(), 3);
-        assert_eq!(result[0], TextElement::ListEntry(elem1[4..].into()));
-        assert_eq!(result[1], TextElement::ListEntry(elem2[4..].into()));
-        assert_eq!(result[2], TextElement::ListEntry(elem3[4..].into()));
+        assert_eq!(result[0], TextElement::ListEntry(1, elem1[4..].into()));
+        assert_eq!(result[1], TextElement::ListEntry(1, elem2[4..].into()));
+        assert_eq!(result[2], TextElement::ListEntry(1, elem3[4..].into()));
+    }
+
+    #[test]
+    fn indentation_gives_list_entry_depth() {
+        let input = "- top\n  - nested\n    - deeper\n- top again";
+        let result = dbg!(parse(input));
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[0], TextElement::ListEntry(0, "top".into()));
+        assert_eq!(result[1], TextElement::ListEntry(2, "nested".into()));
+        assert_eq!(result[2], TextElement::ListEntry(4, "deeper".into()));
+        assert_eq!(result[3], TextElement::ListEntry(0, "top again".into()));
+    }
+
+    #[test]
+    fn nested_list_entry_after_paragraph_keeps_depth() {
+        let input = "Changes:\n  * nested";
+        let result = dbg!(parse(input));
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0], TextElement::Paragraph("Changes:".into()));
+        assert_eq!(result[1], TextElement::ListEntry(2, "nested".into()));
+    }
+
+    #[test]
+    fn tabs_count_as_tab_width_spaces() {
+        let input = "- top\n\t- nested\n\t\t- deeper";
+        let result = dbg!(parse_with_tab_width(input, 2));
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], TextElement::ListEntry(0, "top".into()));
+        assert_eq!(result[1], TextElement::ListEntry(2, "nested".into()));
+        assert_eq!(result[2], TextElement::ListEntry(4, "deeper".into()));
     }
 
     #[test]
@@ -162,9 +1409,9 @@ mod test {
         let input = format!("{elem1}\n{elem2}\n{elem3}\n");
         let result = dbg!(parse(&input));
         assert_eq!(result.len(), 3);
-        assert_eq!(result[0], TextElement::ListEntry(elem1[4..].into()));
-        assert_eq!(result[1], TextElement::ListEntry(elem2[4..].into()));
-        assert_eq!(result[2], TextElement::ListEntry(elem3[4..].into()));
+        assert_eq!(result[0], TextElement::ListEntry(1, elem1[4..].into()));
+        assert_eq!(result[1], TextElement::ListEntry(1, elem2[4..].into()));
+        assert_eq!(result[2], TextElement::ListEntry(1, elem3[4..].into()));
     }
 
     #[test]
@@ -176,12 +1423,12 @@ before a list item. This is a paragraph before a list item.
 * item2
 * item3
 This is a paragraph after a list item."#;
-        let result = dbg!(parse(&input));
+        let result = dbg!(parse(input));
         assert_eq!(result.len(), 5);
         assert_eq!(result[0], TextElement::Paragraph("This is a paragraph before a list item. This is a paragraph before a list item. This is a paragraph before a list item.".into()));
-        assert_eq!(result[1], TextElement::ListEntry("item1".into()));
-        assert_eq!(result[2], TextElement::ListEntry("item2".into()));
-        assert_eq!(result[3], TextElement::ListEntry("item3".into()));
+        assert_eq!(result[1], TextElement::ListEntry(0, "item1".into()));
+        assert_eq!(result[2], TextElement::ListEntry(0, "item2".into()));
+        assert_eq!(result[3], TextElement::ListEntry(0, "item3".into()));
         assert_eq!(
             result[4],
             TextElement::Paragraph("This is a paragraph after a list item.".into())
@@ -201,15 +1448,144 @@ before a list item. This is a paragraph before a list item.
 
 
 This is a paragraph after a list item."#;
-        let result = dbg!(parse(&input));
+        let result = dbg!(parse(input));
         assert_eq!(result.len(), 5);
         assert_eq!(result[0], TextElement::Paragraph("This is a paragraph before a list item. This is a paragraph before a list item. This is a paragraph before a list item.".into()));
-        assert_eq!(result[1], TextElement::ListEntry("item1".into()));
-        assert_eq!(result[2], TextElement::ListEntry("item2".into()));
-        assert_eq!(result[3], TextElement::ListEntry("item3".into()));
+        assert_eq!(result[1], TextElement::ListEntry(0, "item1".into()));
+        assert_eq!(result[2], TextElement::ListEntry(0, "item2".into()));
+        assert_eq!(result[3], TextElement::ListEntry(0, "item3".into()));
         assert_eq!(
             result[4],
             TextElement::Paragraph("This is a paragraph after a list item.".into())
         );
     }
+
+    #[test]
+    fn fenced_code_block_between_paragraphs_is_kept_verbatim() {
+        let input = r#"Run this:
+
+```rust
+fn main() {
+    println!("hello");
+}
+```
+
+And then this."#;
+        let result = dbg!(parse(input));
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], TextElement::Paragraph("Run this:".into()));
+        assert_eq!(
+            result[1],
+            TextElement::CodeBlock("fn main() {\n    println!(\"hello\");\n}".into())
+        );
+        assert_eq!(result[2], TextElement::Paragraph("And then this.".into()));
+    }
+
+    #[test]
+    fn code_block_directly_after_paragraph_line() {
+        let input = "Output:\n```\n- not a list\n\n1. nor this\n```\nDone";
+        let result = dbg!(parse(input));
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], TextElement::Paragraph("Output:".into()));
+        assert_eq!(
+            result[1],
+            TextElement::CodeBlock("- not a list\n\n1. nor this".into())
+        );
+        assert_eq!(result[2], TextElement::Paragraph("Done".into()));
+    }
+
+    #[test]
+    fn unterminated_code_block_runs_to_end() {
+        let result = dbg!(parse("```\nline1\nline2"));
+        assert_eq!(result, vec![TextElement::CodeBlock("line1\nline2".into())]);
+    }
+
+    #[test]
+    fn backticks_at_line_start_without_fence_are_paragraph_text() {
+        let result = dbg!(parse("`Foo` was renamed\n`Bar` too"));
+        assert_eq!(
+            result,
+            vec![TextElement::Paragraph("`Foo` was renamed `Bar` too".into())]
+        );
+    }
+
+    #[test]
+    fn sanitize_escapes_control_characters() {
+        assert_eq!(
+            sanitize("red\x1b[31m\r\nline\ttab\x07"),
+            "red\\u{1b}[31m\nline\ttab\\u{7}"
+        );
+    }
+
+    #[test]
+    fn shortcodes_are_replaced_by_emoji() {
+        assert_eq!(emojify("Ship it :rocket::tada:"), "Ship it 🚀🎉");
+        assert_eq!(emojify(":+1: at 12:30:45"), "👍 at 12:30:45");
+        assert_eq!(
+            emojify(":not_an_emoji: a:b :: :"),
+            ":not_an_emoji: a:b :: :"
+        );
+    }
+
+    #[test]
+    fn bold_at_start_of_paragraph() {
+        let result = spans("**Note** this is important");
+        assert_eq!(
+            result,
+            vec![
+                Span::Bold("Note".into()),
+                Span::Plain(" this is important".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn bold_in_middle_of_paragraph() {
+        let result = spans("this is **very** important");
+        assert_eq!(
+            result,
+            vec![
+                Span::Plain("this is ".into()),
+                Span::Bold("very".into()),
+                Span::Plain(" important".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn bold_at_end_of_paragraph() {
+        let result = spans("this is **important**");
+        assert_eq!(
+            result,
+            vec![
+                Span::Plain("this is ".into()),
+                Span::Bold("important".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn underscores_and_single_asterisks_give_italic() {
+        let result = spans("_really_ *quite* __sure__");
+        assert_eq!(
+            result,
+            vec![
+                Span::Italic("really".into()),
+                Span::Plain(" ".into()),
+                Span::Italic("quite".into()),
+                Span::Plain(" ".into()),
+                Span::Bold("sure".into())
+            ]
+        );
+    }
+
+    #[test]
+    fn unmatched_and_intra_word_markers_are_plain() {
+        assert_eq!(
+            spans("snake_case_name"),
+            vec![Span::Plain("snake_case_name".into())]
+        );
+        assert_eq!(spans("2 * 3 = 6"), vec![Span::Plain("2 * 3 = 6".into())]);
+        assert_eq!(spans("**open"), vec![Span::Plain("**open".into())]);
+    }
 }
@@ -0,0 +1,496 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use prlist::azure::Credentials;
+use prlist::{AzureClient, PullRequestQuery};
+use serde_json::json;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const PULL_REQUESTS: &str = "/contoso/webshop/_apis/git/pullrequests";
+
+fn pull_request(id: u32, title: &str, is_draft: bool, description: &str) -> serde_json::Value {
+    json!({
+        "pullRequestId": id,
+        "title": title,
+        "description": description,
+        "isDraft": is_draft,
+        "createdBy": { "displayName": "Jane Doe", "uniqueName": "jane@contoso.com" },
+        "sourceRefName": "refs/heads/feature/widgets",
+        "targetRefName": "refs/heads/main",
+        "creationDate": "2022-11-20T10:15:00Z",
+        "status": "active",
+        "reviewers": [{ "displayName": "Bob", "uniqueName": "bob@contoso.com", "vote": 10 }],
+        "repository": { "id": "1234", "name": "shop" }
+    })
+}
+
+/// A directory to run in, so that no config file or repository cache of the
+/// user is used.
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("prlist-mock-{}-{name}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("pat"), "secret\n").unwrap();
+    dir
+}
+
+/// `PATH` starting with `dir`, so that a fake `az` put there is used.
+fn search_path(dir: &Path) -> std::ffi::OsString {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let paths = std::iter::once(dir.to_path_buf()).chain(std::env::split_paths(&path));
+    std::env::join_paths(paths).unwrap()
+}
+
+fn prlist(dir: &Path, server: &MockServer, args: &[&str]) -> std::process::Output {
+    let positionals = ["pat", "jane@contoso.com", "contoso", "webshop"];
+    prlist_with(dir, server, &positionals, args)
+}
+
+fn prlist_with(
+    dir: &Path,
+    server: &MockServer,
+    positionals: &[&str],
+    args: &[&str],
+) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_prlist"))
+        .args(positionals)
+        .args([
+            "--base-url",
+            &server.uri(),
+            "--color",
+            "never",
+            "--width",
+            "60",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .env("PATH", search_path(dir))
+        .env("HOME", dir)
+        .env("XDG_CONFIG_HOME", dir)
+        .env("XDG_CACHE_HOME", dir)
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pull_requests_are_fetched_and_printed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .and(query_param("searchCriteria.status", "active"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "count": 2,
+            "value": [
+                pull_request(42, "Add widgets", false, "Adds the **widgets**.\n\n- one\n- two"),
+                pull_request(7, "Draft thing", true, ""),
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/contoso/webshop/_apis/git/repositories/1234/pullRequests/42/workitems",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "value": [{ "id": "101" }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("print");
+    let output = prlist(&dir, &server, &[]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let uri = server.uri();
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "\
+Jane Doe: Add widgets (42)
+   {uri}/contoso/webshop/_git/shop/pullrequest/42
+   shop: feature/widgets → main
+   Created 2022-11-20 10:15, active
+   Reviewers: Bob (approved)
+   Approvals: 1 approved, 0 waiting, 0 rejected
+   Linked work items: #101
+
+   Adds the widgets.
+
+   - one
+   - two
+
+"
+        )
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn error_payloads_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "message": "TF200016: The following project does not exist: webshop.",
+            "typeKey": "ProjectDoesNotExistException"
+        })))
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("error");
+    let output = prlist(&dir, &server, &[]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(5));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("TF200016: The following project does not exist"),
+        "{stderr}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refused_credentials_have_their_own_exit_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("auth");
+    let output = prlist(&dir, &server, &[]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(3), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("check the username and PAT"), "{stderr}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn refused_bearer_tokens_are_not_blamed_on_the_pat() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let client = AzureClient::new(
+        reqwest::Client::new(),
+        "contoso",
+        "webshop",
+        Credentials::Bearer("access-token".into()),
+        "7.1",
+    )
+    .with_base_url(&server.uri())
+    .unwrap();
+    let query = PullRequestQuery {
+        repository: None,
+        status: "active",
+        target: None,
+        max: None,
+    };
+    let error = client.list_pull_requests(&query).await.unwrap_err();
+    let message = error.to_string();
+    assert!(message.ends_with("check the access token"), "{message}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn inline_pats_are_used_with_a_warning() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .and(header(
+            "authorization",
+            "Basic amFuZUBjb250b3NvLmNvbTppbmxpbmUtc2VjcmV0",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 0, "value": [] })))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("inline-pat");
+    let output = prlist(&dir, &server, &["--pat", "inline-secret"]);
+    let explicit = prlist(&dir, &server, &["--auth", "pat", "--pat", "inline-secret"]);
+    let without_pat_file = prlist_with(
+        &dir,
+        &server,
+        &["jane@contoso.com", "contoso", "webshop"],
+        &["--pat", "inline-secret"],
+    );
+    let az_cli = prlist(
+        &dir,
+        &server,
+        &["--auth", "az-cli", "--pat", "inline-secret"],
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("WARNING"), "{stderr}");
+    assert!(explicit.status.success(), "{explicit:?}");
+    assert!(without_pat_file.status.success(), "{without_pat_file:?}");
+    let stderr = String::from_utf8(az_cli.stderr).unwrap();
+    assert!(
+        stderr.contains("--pat can't be used with --auth az-cli"),
+        "{stderr}"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn quiet_leaves_stderr_empty() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 0, "value": [] })))
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("quiet");
+    let chatty = prlist(&dir, &server, &["--pat", "inline-secret"]);
+    let quiet = prlist(&dir, &server, &["--pat", "inline-secret", "--quiet"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let stderr = String::from_utf8(chatty.stderr).unwrap();
+    assert!(stderr.contains("No open pull requests."), "{stderr}");
+    assert!(quiet.status.success(), "{quiet:?}");
+    assert_eq!(String::from_utf8(quiet.stderr).unwrap(), "");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn bearer_tokens_are_sent_instead_of_basic_authentication() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .and(header("authorization", "Bearer access-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "count": 1,
+            "value": [pull_request(42, "Add widgets", false, "")]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = AzureClient::new(
+        reqwest::Client::new(),
+        "contoso",
+        "webshop",
+        Credentials::Bearer("access-token".into()),
+        "7.1",
+    )
+    .with_base_url(&server.uri())
+    .unwrap();
+    let query = PullRequestQuery {
+        repository: None,
+        status: "active",
+        target: None,
+        max: None,
+    };
+    let pull_requests = client.list_pull_requests(&query).await.unwrap();
+    assert_eq!(pull_requests.len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn max_holds_when_a_page_is_larger_than_asked_for() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "count": 3,
+            "value": [
+                pull_request(1, "One", false, ""),
+                pull_request(2, "Two", false, ""),
+                pull_request(3, "Three", false, ""),
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = AzureClient::new(
+        reqwest::Client::new(),
+        "contoso",
+        "webshop",
+        Credentials::Bearer("access-token".into()),
+        "7.1",
+    )
+    .with_base_url(&server.uri())
+    .unwrap();
+    let query = PullRequestQuery {
+        repository: None,
+        status: "active",
+        target: None,
+        max: Some(2),
+    };
+    let pull_requests = client.list_pull_requests(&query).await.unwrap();
+    assert_eq!(pull_requests.len(), 2);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn changes_of_closed_pull_requests_are_diffed_by_commit() {
+    let server = MockServer::start().await;
+    let mut merged = pull_request(42, "Add widgets", false, "");
+    merged["status"] = json!("completed");
+    merged["lastMergeSourceCommit"] = json!({ "commitId": "aaaa1111" });
+    merged["lastMergeTargetCommit"] = json!({ "commitId": "bbbb2222" });
+    let mut broken = pull_request(7, "Fix gadgets", false, "");
+    broken["status"] = json!("completed");
+    broken["lastMergeSourceCommit"] = json!({ "commitId": "cccc3333" });
+    broken["lastMergeTargetCommit"] = json!({ "commitId": "dddd4444" });
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .and(query_param("searchCriteria.status", "completed"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "count": 2,
+            "value": [merged, broken]
+        })))
+        .mount(&server)
+        .await;
+    let diffs = "/contoso/webshop/_apis/git/repositories/1234/diffs/commits";
+    Mock::given(method("GET"))
+        .and(path(diffs))
+        .and(query_param("baseVersion", "bbbb2222"))
+        .and(query_param("baseVersionType", "commit"))
+        .and(query_param("targetVersion", "aaaa1111"))
+        .and(query_param("targetVersionType", "commit"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "changeCounts": { "Edit": 2, "Add": 1 }
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(diffs))
+        .and(query_param("targetVersion", "cccc3333"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "message": "TF401175: The version descriptor could not be resolved."
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("changes");
+    let output = prlist(
+        &dir,
+        &server,
+        &["--status", "completed", "--diff-stats", "--no-work-items"],
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout.matches("Changes: ").collect::<Vec<_>>(),
+        ["Changes: "],
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("Changes: 3 files (1 added, 2 edited)"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Fix gadgets"), "{stdout}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn comment_fields_fetch_the_threads() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "count": 1,
+            "value": [pull_request(42, "Add widgets", false, "")]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/contoso/webshop/_apis/git/repositories/1234/pullRequests/42/threads",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "value": [
+                { "status": "active", "comments": [{ "commentType": "text" }] },
+                { "status": "fixed", "comments": [{ "commentType": "text" }] }
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("comments");
+    let output = prlist(
+        &dir,
+        &server,
+        &["--fields", "id,comments", "--no-work-items"],
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "42  2 comments, 1 unresolved\n\n"
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn azure_cli_tokens_are_used_without_pat_file() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(PULL_REQUESTS))
+        .and(header("authorization", "Bearer token-from-az"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "count": 0, "value": [] })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = work_dir("az-cli");
+    let az = dir.join("az");
+    std::fs::write(&az, "#!/bin/sh\necho token-from-az\n").unwrap();
+    std::fs::set_permissions(&az, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let output = prlist_with(
+        &dir,
+        &server,
+        &["jane@contoso.com", "contoso", "webshop"],
+        &["--auth", "az-cli"],
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn saved_pull_requests_are_read_without_pat_file() {
+    let server = MockServer::start().await;
+    let dir = work_dir("from-file");
+    let saved = dir.join("saved.json");
+    let pull_requests = json!([
+        pull_request(1, "Add widgets", false, ""),
+        pull_request(2, "Fix widgets", false, ""),
+    ]);
+    std::fs::write(&saved, pull_requests.to_string()).unwrap();
+    let output = prlist_with(
+        &dir,
+        &server,
+        &["jane@contoso.com", "contoso", "webshop"],
+        &[
+            "--from-file",
+            saved.to_str().unwrap(),
+            "--max",
+            "1",
+            "--oneline",
+        ],
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "#1 Jane Doe: Add widgets\n"
+    );
+}